@@ -1,16 +1,44 @@
 mod routes;
+mod session_store;
 
-use std::net::SocketAddr;
+use std::{net::SocketAddr, time::Duration};
 
 use anyhow::Result;
 use axum::Server;
-use axum_sessions::{async_session::MemoryStore, SameSite, SessionLayer};
+use axum_sessions::{
+    async_session::{MemoryStore, SessionStore},
+    SameSite, SessionLayer,
+};
 use core::AppState;
 
-use self::routes::router;
+pub use self::{
+    routes::router,
+    session_store::{DatabaseSessionStore, SessionConfig, SessionStoreKind},
+};
 
-pub async fn serve(addr: &SocketAddr, state: AppState, secret: &[u8]) -> Result<()> {
-    let store = MemoryStore::new();
+pub async fn serve(
+    addr: &SocketAddr,
+    state: AppState,
+    secret: &[u8],
+    config: &SessionConfig,
+) -> Result<()> {
+    match config.store {
+        SessionStoreKind::Memory => serve_with_store(addr, state, MemoryStore::new(), secret).await,
+        SessionStoreKind::Database => {
+            let store = DatabaseSessionStore::new(state.connection.clone());
+            let interval = Duration::from_secs(config.cleanup_interval_secs);
+            tokio::spawn(store.clone().run_cleanup(interval));
+            serve_with_store(addr, state, store, secret).await
+        }
+    }
+}
+
+async fn serve_with_store(
+    addr: &SocketAddr,
+    state: AppState,
+    store: impl SessionStore,
+    secret: &[u8],
+) -> Result<()> {
     let session_layer = SessionLayer::new(store, secret).with_same_site_policy(SameSite::Lax);
     let app = router(state, session_layer);
     Server::bind(addr).serve(app.into_make_service()).await?;
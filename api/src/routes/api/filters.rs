@@ -0,0 +1,69 @@
+use axum::{
+    extract::{Path, State},
+    routing::{delete, get},
+    Json, Router,
+};
+use core::{
+    services::FilterService,
+    twitter::{FilterRule, InvalidFilterRule},
+    AppState,
+};
+use entity::filter_rule;
+use reqwest::StatusCode;
+
+use crate::routes::CurrentUser;
+
+/// 設定しているルールの一覧を返す
+async fn index(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<Vec<filter_rule::Model>>, StatusCode> {
+    match FilterService::new(state).find_by_user(user.id).await {
+        Ok(rules) => Ok(Json(rules)),
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// ルールを追加する
+async fn create(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Json(body): Json<FilterRule>,
+) -> Result<(StatusCode, Json<filter_rule::Model>), StatusCode> {
+    match FilterService::new(state).create(user.id, body).await {
+        Ok(rule) => Ok((StatusCode::CREATED, Json(rule))),
+        Err(e) if e.is::<InvalidFilterRule>() => {
+            eprintln!("{e}");
+            Err(StatusCode::BAD_REQUEST)
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// ルールを消す
+async fn destroy(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, StatusCode> {
+    match FilterService::new(state).delete(user.id, id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(index).post(create))
+        .route("/:id", delete(destroy))
+}
@@ -0,0 +1,83 @@
+use axum::{
+    extract::State,
+    routing::{delete, get},
+    Json, Router,
+};
+use axum_sessions::extractors::WritableSession;
+use core::{
+    services::{TwitterOAuth2Service, UserProfile, UserService},
+    AppState,
+};
+use reqwest::StatusCode;
+
+use crate::routes::CurrentUser;
+
+/// ログインしているユーザーと連携しているアカウントを返す
+async fn show(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<UserProfile>, StatusCode> {
+    match UserService::new(state).find_profile(user.id).await {
+        Ok(Some(profile)) => Ok(Json(profile)),
+        Ok(None) => Err(StatusCode::UNAUTHORIZED),
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// 退会する。保持期間が過ぎるまでは `/login?reactivate=true` から復帰できる
+async fn destroy(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    mut session: WritableSession,
+) -> Result<StatusCode, StatusCode> {
+    match UserService::new(state).delete_account(user.id).await {
+        Ok(()) => {
+            session.destroy();
+            Ok(StatusCode::NO_CONTENT)
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Twitterの連携を解除する。収集も止まる
+async fn unlink_twitter(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<StatusCode, StatusCode> {
+    match TwitterOAuth2Service::new(user, state).unlink().await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Spotifyの連携を解除する。`/login` から連携し直せる
+async fn unlink_spotify(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<StatusCode, StatusCode> {
+    match UserService::new(state).unlink_spotify(user.id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(StatusCode::NOT_FOUND),
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(show).delete(destroy))
+        .route("/twitter", delete(unlink_twitter))
+        .route("/spotify", delete(unlink_spotify))
+}
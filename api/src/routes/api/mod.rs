@@ -0,0 +1,15 @@
+mod filters;
+mod me;
+mod playlist;
+mod source;
+
+use axum::Router;
+use core::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .nest("/filters", filters::router())
+        .nest("/me", me::router())
+        .nest("/playlist", playlist::router())
+        .nest("/source", source::router())
+}
@@ -0,0 +1,91 @@
+use axum::{extract::State, routing::get, Json, Router};
+use core::{
+    services::{PlaylistNotSelectable, PlaylistService, SpotifyAccountNotFound},
+    spotify::{SimplifiedPlaylist, SimplifiedPlaylists},
+    AppState,
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+
+use crate::routes::CurrentUser;
+
+#[derive(Debug, Serialize)]
+pub struct PlaylistResponse {
+    pub playlist: Option<SimplifiedPlaylist>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SelectPlaylistRequest {
+    pub playlist_id: String,
+}
+
+/// Spotifyを連携していなければ404、それ以外は `status` にする
+fn error_status(e: anyhow::Error, status: StatusCode) -> StatusCode {
+    eprintln!("{e}");
+    if e.is::<SpotifyAccountNotFound>() {
+        StatusCode::NOT_FOUND
+    } else if e.is::<PlaylistNotSelectable>() {
+        StatusCode::FORBIDDEN
+    } else {
+        status
+    }
+}
+
+/// 今設定されているプレイリストを返す
+async fn show(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<PlaylistResponse>, StatusCode> {
+    let service = PlaylistService::new(user, state);
+    match service.current().await {
+        Ok(playlist) => Ok(Json(PlaylistResponse { playlist })),
+        Err(e) => Err(error_status(e, StatusCode::INTERNAL_SERVER_ERROR)),
+    }
+}
+
+/// 既存のプレイリストを選択する
+async fn select(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Json(body): Json<SelectPlaylistRequest>,
+) -> Result<Json<PlaylistResponse>, StatusCode> {
+    let service = PlaylistService::new(user, state);
+    match service.select(&body.playlist_id).await {
+        Ok(playlist) => Ok(Json(PlaylistResponse {
+            playlist: Some(playlist),
+        })),
+        Err(e) => Err(error_status(e, StatusCode::BAD_REQUEST)),
+    }
+}
+
+/// mikage用のプレイリストを作成して選択する
+async fn create(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<PlaylistResponse>, StatusCode> {
+    let service = PlaylistService::new(user, state);
+    match service.create().await {
+        Ok(playlist) => Ok(Json(PlaylistResponse {
+            playlist: Some(playlist),
+        })),
+        Err(e) => Err(error_status(e, StatusCode::INTERNAL_SERVER_ERROR)),
+    }
+}
+
+/// 選択できるプレイリストの一覧を返す
+async fn candidates(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<SimplifiedPlaylists>, StatusCode> {
+    let service = PlaylistService::new(user, state);
+    match service.candidates().await {
+        Ok(playlists) => Ok(Json(playlists)),
+        Err(e) => Err(error_status(e, StatusCode::INTERNAL_SERVER_ERROR)),
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/", get(show).put(select).post(create))
+        .route("/candidates", get(candidates))
+}
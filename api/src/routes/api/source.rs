@@ -0,0 +1,54 @@
+use axum::{extract::State, routing::get, Json, Router};
+use core::{
+    services::{SourceService, TwitterAccountNotFound},
+    twitter::TweetSource,
+    AppState,
+};
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::routes::CurrentUser;
+
+#[derive(Debug, Serialize)]
+pub struct SourceResponse {
+    pub source: TweetSource,
+}
+
+/// 今ツイートを集めているところを返す
+async fn show(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+) -> Result<Json<SourceResponse>, StatusCode> {
+    let service = SourceService::new(user, state);
+    match service.current().await {
+        Ok(source) => Ok(Json(SourceResponse { source })),
+        Err(e) if e.is::<TwitterAccountNotFound>() => {
+            eprintln!("{e}");
+            Err(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// ツイートを集めるところを選択する
+async fn select(
+    State(state): State<AppState>,
+    CurrentUser(user): CurrentUser,
+    Json(body): Json<TweetSource>,
+) -> Result<Json<SourceResponse>, StatusCode> {
+    let service = SourceService::new(user, state);
+    match service.select(body).await {
+        Ok(source) => Ok(Json(SourceResponse { source })),
+        Err(e) => {
+            eprintln!("{e}");
+            Err(StatusCode::BAD_REQUEST)
+        }
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/", get(show).put(select))
+}
@@ -0,0 +1,43 @@
+use axum::{async_trait, extract::FromRequestParts, http::request::Parts};
+use axum_sessions::extractors::ReadableSession;
+use core::{services::UserService, AppState};
+use entity::user;
+use reqwest::StatusCode;
+
+/// セッションでログインしているユーザー
+///
+/// ログインしていないか、ユーザーが退会していれば401で断る。
+/// 他のセッションで退会したときも、このセッションからは操作できなくなる。
+pub struct CurrentUser(pub user::Model);
+
+#[async_trait]
+impl FromRequestParts<AppState> for CurrentUser {
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<CurrentUser, StatusCode> {
+        // 後からWritableSessionを取り出せるように、読んだらすぐにロックを外す
+        let user_id = {
+            let session = ReadableSession::from_request_parts(parts, state)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            session.get::<i32>("user_id")
+        };
+        let Some(user_id) = user_id else {
+            return Err(StatusCode::UNAUTHORIZED);
+        };
+        match UserService::new(state.clone())
+            .find_active_user(user_id)
+            .await
+        {
+            Ok(Some(user)) => Ok(CurrentUser(user)),
+            Ok(None) => Err(StatusCode::UNAUTHORIZED),
+            Err(e) => {
+                eprintln!("{e}");
+                Err(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+        }
+    }
+}
@@ -1,9 +1,11 @@
+mod api;
+mod current_user;
 mod twitter;
 
 use axum::{
     extract::{Query, State},
     http::HeaderMap,
-    response::{IntoResponse, Html},
+    response::{Html, IntoResponse, Response},
     routing::get,
     Router,
 };
@@ -12,10 +14,27 @@ use axum_sessions::{
     extractors::{ReadableSession, WritableSession},
     SessionLayer,
 };
-use core::{services::UserService, AppState};
+use core::{
+    services::{UserDeleted, UserService},
+    AppState,
+};
 use reqwest::{header::LOCATION, StatusCode};
 use serde::Deserialize;
 
+use self::current_user::CurrentUser;
+
+/// 認可を始めたセッションに `state` を保存しておくキー
+const SPOTIFY_OAUTH2_STATE_KEY: &str = "spotify_oauth2_state";
+/// 退会したユーザーを復帰させるかどうかを保存しておくキー
+const SPOTIFY_OAUTH2_REACTIVATE_KEY: &str = "spotify_oauth2_reactivate";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoginQueryParam {
+    /// 退会したユーザーを復帰させる
+    #[serde(default)]
+    pub reactivate: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CallbackQueryParam {
     pub code: String,
@@ -29,20 +48,55 @@ async fn index(session: ReadableSession) -> impl IntoResponse {
     Html(format!(r#"Not logged in <a href="/login">Login</a>"#))
 }
 
-async fn login(State(state): State<AppState>, session: ReadableSession) -> impl IntoResponse {
-    if let Some(user_id) = session.get::<i32>("user_id") {
-        println!("already login as {user_id}");
-        let mut header = HeaderMap::new();
-        header.append(LOCATION, "/".parse().unwrap());
-        return (StatusCode::TEMPORARY_REDIRECT, header);
+/// コールバックの `state` が、このセッションで始めた認可のものか確かめる
+///
+/// 一度しか使えないように、確かめたらセッションから消す。
+fn verify_oauth2_state(session: &mut WritableSession, key: &str, state: &str) -> bool {
+    let expected = session.get::<String>(key);
+    session.remove(key);
+    expected.as_deref() == Some(state)
+}
+
+async fn login(
+    Query(query): Query<LoginQueryParam>,
+    State(state): State<AppState>,
+    user: Result<CurrentUser, StatusCode>,
+    mut session: WritableSession,
+) -> impl IntoResponse {
+    let service = UserService::new(state);
+    match user {
+        // Spotifyの連携を解除していれば連携し直す
+        Ok(CurrentUser(user)) => match service.find_spotify_account(user.id).await {
+            Ok(Some(_)) => {
+                println!("already login as {}", user.id);
+                let mut header = HeaderMap::new();
+                header.append(LOCATION, "/".parse().unwrap());
+                return (StatusCode::TEMPORARY_REDIRECT, header);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{e}");
+                return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
+            }
+        },
+        // 退会したユーザーはコールバックで復帰させるかを聞く
+        Err(StatusCode::UNAUTHORIZED) => {}
+        Err(status) => return (status, HeaderMap::new()),
     }
-    let url = match UserService::new(state).create_spotify_redirect_url() {
-        Ok(u) => u,
+    let (url, oauth2_state) = match service.create_spotify_redirect_url().await {
+        Ok(v) => v,
         Err(e) => {
             eprintln!("{e}");
             return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
         }
     };
+    if let Err(e) = session
+        .insert(SPOTIFY_OAUTH2_STATE_KEY, oauth2_state)
+        .and_then(|_| session.insert(SPOTIFY_OAUTH2_REACTIVATE_KEY, query.reactivate))
+    {
+        eprintln!("{e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
+    }
     let mut header = HeaderMap::new();
     header.append(LOCATION, url.to_string().parse().unwrap());
     (StatusCode::TEMPORARY_REDIRECT, header)
@@ -52,15 +106,32 @@ async fn callback(
     Query(query): Query<CallbackQueryParam>,
     State(state): State<AppState>,
     mut session: WritableSession,
-) -> impl IntoResponse {
-    let (user, _spotify) = match UserService::new(state)
-        .exchange_spotify_code(query.code, query.state)
-        .await
-    {
+) -> Response {
+    if !verify_oauth2_state(&mut session, SPOTIFY_OAUTH2_STATE_KEY, &query.state) {
+        eprintln!("OAuth2 state does not match the session");
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let current_user_id = session.get::<i32>("user_id");
+    let reactivate = session
+        .get::<bool>(SPOTIFY_OAUTH2_REACTIVATE_KEY)
+        .unwrap_or(false);
+    session.remove(SPOTIFY_OAUTH2_REACTIVATE_KEY);
+    let result = UserService::new(state)
+        .exchange_spotify_code(query.code, query.state, current_user_id, reactivate)
+        .await;
+    let (user, _spotify) = match result {
         Ok(v) => v,
+        Err(e) if e.is::<UserDeleted>() => {
+            eprintln!("{e}");
+            let body = Html(concat!(
+                "This account has been deleted. ",
+                r#"<a href="/login?reactivate=true">Reactivate it</a>"#,
+            ));
+            return (StatusCode::FORBIDDEN, body).into_response();
+        }
         Err(e) => {
             eprintln!("{e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
     let mut header = HeaderMap::new();
@@ -68,13 +139,13 @@ async fn callback(
 
     if let Err(e) = session.insert("user_id", user.id) {
         eprintln!("{e}");
-        return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
     }
 
     // ここでリダイレクトするからsessionにinsertしても飛んじゃうっぽい（どうしたらいい...
     // リダイレクトから戻ってきたところだからっぽい (Spotifyから飛ばされて戻ってきたとこ)
     // Laxにしておく必要があるっぽい
-    (StatusCode::TEMPORARY_REDIRECT, header)
+    (StatusCode::TEMPORARY_REDIRECT, header).into_response()
 }
 
 pub fn router(state: AppState, session_layer: SessionLayer<impl SessionStore>) -> Router {
@@ -83,6 +154,7 @@ pub fn router(state: AppState, session_layer: SessionLayer<impl SessionStore>) -
         .route("/login", get(login))
         .route("/callback", get(callback))
         .nest("/twitter", twitter::router())
+        .nest("/api", api::router())
         .layer(session_layer)
         .with_state(state)
 }
@@ -1,39 +1,69 @@
 use axum::{
     extract::{Query, State},
     http::HeaderMap,
-    response::IntoResponse,
+    response::{Html, IntoResponse, Response},
     routing::get,
     Router,
 };
-use axum_sessions::extractors::ReadableSession;
-use core::{services::TwitterOAuth2Service, AppState};
+use axum_sessions::extractors::WritableSession;
+use core::{
+    services::{OAuth2VerifierMismatch, TwitterAccountConflict, TwitterOAuth2Service},
+    AppState,
+};
 use reqwest::{header::LOCATION, StatusCode};
 use serde::Deserialize;
 
+use super::{verify_oauth2_state, CurrentUser};
+
+/// 認可を始めたセッションに `state` を保存しておくキー
+const TWITTER_OAUTH2_STATE_KEY: &str = "twitter_oauth2_state";
+/// 他のユーザーに紐付いているアカウントを付け替えるかどうかを保存しておくキー
+const TWITTER_OAUTH2_TRANSFER_KEY: &str = "twitter_oauth2_transfer";
+
+#[derive(Debug, Default, Deserialize)]
+pub struct LoginQueryParam {
+    /// 他のユーザーに紐付いているアカウントを、認可し直してこのユーザーに付け替える
+    #[serde(default)]
+    pub transfer: bool,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct CallbackQueryParam {
     pub code: String,
     pub state: String,
 }
 
-async fn login(State(state): State<AppState>, session: ReadableSession) -> impl IntoResponse {
-    // FormRequestから勝手にやりたい
-    let Some(user_id) = session.get::<i32>("user_id") else {
-        let mut header = HeaderMap::new();
-        header.append(LOCATION, "/".parse().unwrap());
-        return (StatusCode::TEMPORARY_REDIRECT, header);
+async fn login(
+    Query(query): Query<LoginQueryParam>,
+    State(state): State<AppState>,
+    user: Result<CurrentUser, StatusCode>,
+    mut session: WritableSession,
+) -> impl IntoResponse {
+    let user = match user {
+        Ok(CurrentUser(user)) => user,
+        Err(StatusCode::UNAUTHORIZED) => {
+            let mut header = HeaderMap::new();
+            header.append(LOCATION, "/".parse().unwrap());
+            return (StatusCode::TEMPORARY_REDIRECT, header);
+        }
+        Err(status) => return (status, HeaderMap::new()),
     };
 
-    let Ok(service) = TwitterOAuth2Service::new_with_user_id(state, user_id).await else {
-        return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new())
-    };
-    let url = match service.create_twitter_redirect_url() {
-        Ok(u) => u,
+    let service = TwitterOAuth2Service::new(user, state);
+    let (url, oauth2_state) = match service.create_twitter_redirect_url().await {
+        Ok(v) => v,
         Err(e) => {
             eprintln!("{e}");
             return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
         }
     };
+    if let Err(e) = session
+        .insert(TWITTER_OAUTH2_STATE_KEY, oauth2_state)
+        .and_then(|_| session.insert(TWITTER_OAUTH2_TRANSFER_KEY, query.transfer))
+    {
+        eprintln!("{e}");
+        return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
+    }
     let mut header = HeaderMap::new();
     header.append(LOCATION, url.to_string().parse().unwrap());
     (StatusCode::TEMPORARY_REDIRECT, header)
@@ -42,26 +72,47 @@ async fn login(State(state): State<AppState>, session: ReadableSession) -> impl
 async fn callback(
     Query(query): Query<CallbackQueryParam>,
     State(state): State<AppState>,
-    session: ReadableSession,
-) -> impl IntoResponse {
-    // FormRequestから勝手にやりたい
-    let Some(user_id) = session.get::<i32>("user_id") else {
-        let mut header = HeaderMap::new();
-        header.append(LOCATION, "/".parse().unwrap());
-        return (StatusCode::TEMPORARY_REDIRECT, header);
-    };
-    let service = match TwitterOAuth2Service::new_with_user_id(state, user_id).await {
-        Ok(s) => s,
-        Err(e) => {
-            eprintln!("{e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new())
+    user: Result<CurrentUser, StatusCode>,
+    mut session: WritableSession,
+) -> Response {
+    let user = match user {
+        Ok(CurrentUser(user)) => user,
+        Err(StatusCode::UNAUTHORIZED) => {
+            let mut header = HeaderMap::new();
+            header.append(LOCATION, "/".parse().unwrap());
+            return (StatusCode::TEMPORARY_REDIRECT, header).into_response();
         }
+        Err(status) => return status.into_response(),
     };
-    let _twitter = match service.exchange_spotify_code(query.code, query.state).await {
+    if !verify_oauth2_state(&mut session, TWITTER_OAUTH2_STATE_KEY, &query.state) {
+        eprintln!("OAuth2 state does not match the session");
+        return StatusCode::BAD_REQUEST.into_response();
+    }
+    let service = TwitterOAuth2Service::new(user, state);
+    let transfer = session
+        .get::<bool>(TWITTER_OAUTH2_TRANSFER_KEY)
+        .unwrap_or(false);
+    session.remove(TWITTER_OAUTH2_TRANSFER_KEY);
+    let result = service
+        .exchange_spotify_code(query.code, query.state, transfer)
+        .await;
+    let _twitter = match result {
         Ok(v) => v,
+        Err(e) if e.is::<OAuth2VerifierMismatch>() => {
+            eprintln!("{e}");
+            return StatusCode::FORBIDDEN.into_response();
+        }
+        Err(e) if e.is::<TwitterAccountConflict>() => {
+            eprintln!("{e}");
+            let body = Html(concat!(
+                "This Twitter account is already linked to another user. ",
+                r#"<a href="/twitter/login?transfer=true">Transfer it to this user</a>"#,
+            ));
+            return (StatusCode::CONFLICT, body).into_response();
+        }
         Err(e) => {
             eprintln!("{e}");
-            return (StatusCode::INTERNAL_SERVER_ERROR, HeaderMap::new());
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
         }
     };
     let mut header = HeaderMap::new();
@@ -72,7 +123,7 @@ async fn callback(
     // ここでリダイレクトするからsessionにinsertしても飛んじゃうっぽい（どうしたらいい...
     // リダイレクトから戻ってきたところだからっぽい (Spotifyから飛ばされて戻ってきたとこ)
     // Laxにしておく必要があるっぽい
-    (StatusCode::TEMPORARY_REDIRECT, header)
+    (StatusCode::TEMPORARY_REDIRECT, header).into_response()
 }
 
 pub fn router() -> Router<AppState> {
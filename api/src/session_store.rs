@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use axum_sessions::async_session::{async_trait, serde_json, Result, Session, SessionStore};
+use chrono::Utc;
+use entity::session;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, sea_query::OnConflict, ColumnTrait, Condition,
+    DatabaseConnection, EntityTrait, QueryFilter, Set,
+};
+use serde::Deserialize;
+
+/// セッションの保存先
+#[derive(Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStoreKind {
+    /// 再起動するとログアウトされる。開発用
+    Memory,
+    #[default]
+    Database,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(default)]
+pub struct SessionConfig {
+    pub store: SessionStoreKind,
+    /// 期限切れのセッションを消す間隔 (秒)
+    pub cleanup_interval_secs: u64,
+}
+
+impl Default for SessionConfig {
+    fn default() -> SessionConfig {
+        SessionConfig {
+            store: SessionStoreKind::default(),
+            cleanup_interval_secs: 60 * 60,
+        }
+    }
+}
+
+/// `sessions` テーブルにセッションを保存する
+#[derive(Clone, Debug)]
+pub struct DatabaseSessionStore {
+    connection: DatabaseConnection,
+}
+
+impl DatabaseSessionStore {
+    pub fn new(connection: DatabaseConnection) -> DatabaseSessionStore {
+        DatabaseSessionStore { connection }
+    }
+
+    /// 期限切れのセッションを消す
+    pub async fn cleanup(&self) -> anyhow::Result<u64> {
+        let result = session::Entity::delete_many()
+            .filter(session::Column::ExpiresAt.lt(now()))
+            .exec(&self.connection)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// `interval` ごとに [`DatabaseSessionStore::cleanup`] を行う
+    pub async fn run_cleanup(self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.cleanup().await {
+                eprintln!("{e}");
+            }
+        }
+    }
+}
+
+fn now() -> DateTimeWithTimeZone {
+    Utc::now().into()
+}
+
+#[async_trait]
+impl SessionStore for DatabaseSessionStore {
+    async fn load_session(&self, cookie_value: String) -> Result<Option<Session>> {
+        let id = Session::id_from_cookie_value(&cookie_value)?;
+        let model = session::Entity::find_by_id(id)
+            .filter(
+                Condition::any()
+                    .add(session::Column::ExpiresAt.is_null())
+                    .add(session::Column::ExpiresAt.gt(now())),
+            )
+            .one(&self.connection)
+            .await?;
+        let Some(model) = model else {
+            return Ok(None);
+        };
+        let session: Session = serde_json::from_str(&model.session)?;
+        Ok(session.validate())
+    }
+
+    async fn store_session(&self, session: Session) -> Result<Option<String>> {
+        let model = session::ActiveModel {
+            id: Set(session.id().to_string()),
+            session: Set(serde_json::to_string(&session)?),
+            expires_at: Set(session.expiry().map(|expiry| (*expiry).into())),
+        };
+        session::Entity::insert(model)
+            .on_conflict(
+                OnConflict::column(session::Column::Id)
+                    .update_columns([session::Column::Session, session::Column::ExpiresAt])
+                    .to_owned(),
+            )
+            .exec(&self.connection)
+            .await?;
+        Ok(session.into_cookie_value())
+    }
+
+    async fn destroy_session(&self, session: Session) -> Result {
+        session::Entity::delete_by_id(session.id().to_string())
+            .exec(&self.connection)
+            .await?;
+        Ok(())
+    }
+
+    async fn clear_store(&self) -> Result {
+        session::Entity::delete_many()
+            .exec(&self.connection)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,311 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use entity::{spotify_account, twitter_account, user};
+use sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use serde::Deserialize;
+
+use crate::{
+    services::{CollectionStateService, FilterService, TokenService, TrackService, UserService},
+    spotify::{AddTracksError, HttpShortLinkResolver, ResolveShortLink, SpotifyLinkExtractor},
+    twitter::{GetTimeline, TimelineTruncated, Tweet, TweetFilter},
+    AppState,
+};
+
+#[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(default)]
+pub struct CollectorConfig {
+    /// タイムラインを取得する間隔 (秒)
+    pub interval_secs: u64,
+    pub duplicate_policy: DuplicatePolicy,
+}
+
+impl Default for CollectorConfig {
+    fn default() -> CollectorConfig {
+        CollectorConfig {
+            interval_secs: 300,
+            duplicate_policy: DuplicatePolicy::default(),
+        }
+    }
+}
+
+/// 既に拾った楽曲をどう扱うか
+///
+/// ```toml
+/// duplicate_policy = "skip"
+/// duplicate_policy = { allow_after_days = 30 }
+/// ```
+#[derive(Deserialize, PartialEq, Eq, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// 一度でも拾ったものは追加しない
+    #[default]
+    Skip,
+    /// 最後に追加してから指定した日数が経っていれば再び追加する
+    AllowAfterDays(u32),
+}
+
+impl DuplicatePolicy {
+    /// これより後に追加されていれば重複とみなす (`None` なら期間を問わない)
+    pub fn threshold(&self) -> Option<DateTime<Utc>> {
+        match self {
+            DuplicatePolicy::Skip => None,
+            DuplicatePolicy::AllowAfterDays(days) => {
+                Some(Utc::now() - ChronoDuration::days(i64::from(*days)))
+            }
+        }
+    }
+}
+
+/// 一定間隔でタイムラインを取得し続けて、見つけた楽曲をプレイリストに追加する
+///
+/// 短縮URLは `R` で展開する。
+#[derive(Clone, Debug)]
+pub struct Collector<R = HttpShortLinkResolver> {
+    state: AppState,
+    config: CollectorConfig,
+    links: SpotifyLinkExtractor<R>,
+}
+
+impl Collector {
+    pub fn new(state: AppState, config: CollectorConfig) -> Collector {
+        let resolver = HttpShortLinkResolver::new(state.http_client.clone());
+        Collector::with_resolver(state, config, resolver)
+    }
+}
+
+impl<R: ResolveShortLink + Send + Sync> Collector<R> {
+    pub fn with_resolver(state: AppState, config: CollectorConfig, resolver: R) -> Collector<R> {
+        Collector {
+            state,
+            config,
+            links: SpotifyLinkExtractor::new(resolver),
+        }
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.state.connection
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval_secs));
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.collect().await {
+                eprintln!("{e}");
+            }
+        }
+    }
+
+    /// SpotifyとTwitterの両方を紐付けているユーザー全員分を収集する
+    pub async fn collect(&self) -> Result<()> {
+        let accounts = twitter_account::Entity::find()
+            .find_also_related(user::Entity)
+            .all(self.connection())
+            .await?;
+        for (twitter, user) in accounts {
+            let Some(user) = user else {
+                continue;
+            };
+            // 退会したユーザー
+            if user.deleted_at.is_some() {
+                continue;
+            }
+            let spotify = UserService::linked_spotify_accounts()
+                .filter(spotify_account::Column::OwnerUserId.eq(user.id))
+                .one(self.connection())
+                .await;
+            let spotify = match spotify {
+                Ok(Some(spotify)) => spotify,
+                Ok(None) => continue,
+                // 1人分の失敗で他のユーザーの収集を止めない
+                Err(e) => {
+                    eprintln!("failed to find spotify account for user {}: {e}", user.id);
+                    continue;
+                }
+            };
+            // 追加先のプレイリストが決まっていない
+            let Some(playlist_id) = &spotify.playlist_id else {
+                continue;
+            };
+            let states = CollectionStateService::new(self.state.clone());
+            let recorded = match self.collect_account(&twitter, &spotify, playlist_id).await {
+                Ok(count) => {
+                    println!("collected {count} tracks for user {}", user.id);
+                    states.record_success(&twitter.user_id).await
+                }
+                Err(e) => {
+                    eprintln!("failed to collect for user {}: {e}", user.id);
+                    states.record_failure(&twitter.user_id, e.to_string()).await
+                }
+            };
+            if let Err(e) = recorded {
+                eprintln!(
+                    "failed to record collection state for user {}: {e}",
+                    user.id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn collect_account(
+        &self,
+        twitter: &twitter_account::Model,
+        spotify: &spotify_account::Model,
+        playlist_id: &str,
+    ) -> Result<usize> {
+        let tokens = TokenService::new(self.state.clone());
+        let states = CollectionStateService::new(self.state.clone());
+        let since_id = states.find_cursor(&twitter.user_id).await?;
+        let filter = FilterService::new(self.state.clone())
+            .tweet_filter(spotify.owner_user_id)
+            .await?;
+        let (tweets, newest_tweet_id, truncated) = match self
+            .read_timeline(&tokens, twitter, &filter, since_id.clone())
+            .await
+        {
+            Err(e) if TokenService::is_unauthorized(&e) => {
+                let twitter = tokens.refresh_twitter_account(twitter).await?;
+                self.read_timeline(&tokens, &twitter, &filter, since_id)
+                    .await?
+            }
+            result => result?,
+        };
+        let mut found = Vec::new();
+        for tweet in &tweets {
+            for url in tweet.all_urls() {
+                match self.links.extract_track_uri(url).await {
+                    Ok(Some(uri)) => found.push((tweet, url, uri)),
+                    Ok(None) => {}
+                    Err(e) => eprintln!("failed to resolve {url}: {e}"),
+                }
+            }
+        }
+        if found.is_empty() {
+            states
+                .save_cursor(&twitter.user_id, newest_tweet_id)
+                .await?;
+            if let Some(e) = truncated {
+                return Err(e.into());
+            }
+            return Ok(0);
+        }
+
+        let track_uris = found
+            .iter()
+            .map(|(_, _, uri)| uri.clone())
+            .collect::<Vec<_>>();
+        let (mut added, error) = match self
+            .add_tracks(&tokens, spotify, playlist_id, track_uris.clone())
+            .await
+        {
+            Err(e) if TokenService::is_unauthorized(&e) => {
+                let spotify = tokens.refresh_spotify_account(spotify).await?;
+                self.add_tracks(&tokens, &spotify, playlist_id, track_uris)
+                    .await?
+            }
+            result => result?,
+        };
+        let count = added.len();
+
+        let tracks = TrackService::new(self.state.clone());
+        for (tweet, url, uri) in &found {
+            // 同じ楽曲を複数のツイートから拾ったときは最初のものを記録する
+            let Some(position) = added.iter().position(|added| added == uri) else {
+                continue;
+            };
+            added.remove(position);
+            tracks
+                .insert(spotify.owner_user_id, uri.clone(), url, tweet)
+                .await?;
+        }
+        // 追加できなかった楽曲を次回拾い直せるように、読んだところを進めない
+        if let Some(e) = error {
+            return Err(e.into());
+        }
+        states
+            .save_cursor(&twitter.user_id, newest_tweet_id)
+            .await?;
+        // 読み飛ばしたことを `last_error` に残す
+        if let Some(e) = truncated {
+            return Err(e.into());
+        }
+        Ok(count)
+    }
+
+    /// `since_id` より新しいツイートのうち `filter` で選んだものと、次に読み始めるところを返す
+    ///
+    /// さかのぼりきれずに読み飛ばしたときは [`TimelineTruncated`] も返す。
+    async fn read_timeline(
+        &self,
+        tokens: &TokenService,
+        twitter: &twitter_account::Model,
+        filter: &TweetFilter,
+        since_id: Option<String>,
+    ) -> Result<(Vec<Tweet>, Option<String>, Option<TimelineTruncated>)> {
+        let mut reader = tokens.timeline_reader(twitter).await?;
+        reader.set_since_id(since_id);
+        let (tweets, truncated) = reader.get_timeline().await?;
+        let tweets = tweets
+            .into_iter()
+            .filter(|tweet| filter.accepts(tweet, reader.me()))
+            .collect();
+        Ok((
+            tweets,
+            reader.since_id().map(ToString::to_string),
+            truncated,
+        ))
+    }
+
+    async fn add_tracks(
+        &self,
+        tokens: &TokenService,
+        spotify: &spotify_account::Model,
+        playlist_id: &str,
+        track_uris: Vec<String>,
+    ) -> Result<(Vec<String>, Option<AddTracksError>)> {
+        let client = tokens.spotify_client(spotify).await?;
+        let items = client.get_playlist_tracks(playlist_id).await?;
+        let threshold = self.config.duplicate_policy.threshold();
+        let tracks = TrackService::new(self.state.clone());
+
+        let mut new_track_uris = Vec::<String>::new();
+        for uri in track_uris {
+            if new_track_uris.contains(&uri) {
+                continue;
+            }
+            let in_playlist = items.iter().any(|item| {
+                item.uri() == Some(uri.as_str())
+                    && match (threshold, item.added_at) {
+                        (Some(threshold), Some(added_at)) => added_at > threshold,
+                        _ => true,
+                    }
+            });
+            if in_playlist {
+                continue;
+            }
+            if tracks
+                .exists_since(spotify.owner_user_id, &uri, threshold)
+                .await?
+            {
+                continue;
+            }
+            new_track_uris.push(uri);
+        }
+
+        if new_track_uris.is_empty() {
+            return Ok((new_track_uris, None));
+        }
+        match client
+            .add_tracks_to_playlist(playlist_id, new_track_uris, None)
+            .await
+        {
+            Ok(added) => Ok((added.into_iter().flat_map(|a| a.uris).collect(), None)),
+            // 一部だけ追加できたときは、追加できた分を記録できるようにする
+            Err(e) if !e.added.is_empty() => Ok((e.added_uris(), Some(e))),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use oauth2::{HttpRequest, HttpResponse};
+use reqwest::{redirect::Policy, Proxy};
+use serde::Deserialize;
+
+/// 全てのAPI呼び出しで共有するHTTPクライアントの設定
+#[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(default)]
+pub struct HttpConfig {
+    /// 接続するまでのタイムアウト (秒)
+    pub connect_timeout_secs: u64,
+    /// リクエストを送ってからレスポンスを読み終えるまでのタイムアウト (秒)
+    pub timeout_secs: u64,
+    /// `http://proxy.example.com:8080` など。指定しなければ環境変数に従う
+    pub proxy: Option<String>,
+    /// ホストごとに保持しておくアイドルな接続の数
+    pub pool_max_idle_per_host: Option<usize>,
+    /// アイドルな接続を閉じるまでの時間 (秒)
+    pub pool_idle_timeout_secs: u64,
+    pub user_agent: String,
+}
+
+impl Default for HttpConfig {
+    fn default() -> HttpConfig {
+        HttpConfig {
+            connect_timeout_secs: 10,
+            timeout_secs: 30,
+            proxy: None,
+            pool_max_idle_per_host: None,
+            pool_idle_timeout_secs: 90,
+            user_agent: concat!("mikage/", env!("CARGO_PKG_VERSION")).to_string(),
+        }
+    }
+}
+
+impl HttpConfig {
+    /// `follow_redirects` が `false` ならリダイレクトを辿らないクライアントを作る
+    pub fn build(&self, follow_redirects: bool) -> reqwest::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(self.connect_timeout_secs))
+            .timeout(Duration::from_secs(self.timeout_secs))
+            .pool_idle_timeout(Duration::from_secs(self.pool_idle_timeout_secs))
+            .user_agent(&self.user_agent);
+        if let Some(max) = self.pool_max_idle_per_host {
+            builder = builder.pool_max_idle_per_host(max);
+        }
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+        if !follow_redirects {
+            builder = builder.redirect(Policy::none());
+        }
+        builder.build()
+    }
+}
+
+/// `oauth2::reqwest::async_http_client` を共有のクライアントで行う版
+pub(crate) async fn oauth2_http_client(
+    client: reqwest::Client,
+    request: HttpRequest,
+) -> Result<HttpResponse, oauth2::reqwest::Error<reqwest::Error>> {
+    use oauth2::reqwest::Error;
+    let mut request_builder = client
+        .request(request.method, request.url.as_str())
+        .body(request.body);
+    for (name, value) in &request.headers {
+        request_builder = request_builder.header(name.as_str(), value.as_bytes());
+    }
+    let request = request_builder.build().map_err(Error::Reqwest)?;
+    let response = client.execute(request).await.map_err(Error::Reqwest)?;
+    let status_code = response.status();
+    let headers = response.headers().to_owned();
+    let body = response.bytes().await.map_err(Error::Reqwest)?;
+    Ok(HttpResponse {
+        status_code,
+        headers,
+        body: body.to_vec(),
+    })
+}
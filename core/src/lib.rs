@@ -1,7 +1,23 @@
+mod collector;
+mod http;
+mod purger;
+mod retry;
 pub mod services;
 mod state;
+mod token;
+mod verifier;
 
 pub mod spotify;
 pub mod twitter;
 
-pub use self::{spotify::SpotifyOAuth2Client, state::*, twitter::TwitterOAuth2Client};
+pub use self::{
+    collector::{Collector, CollectorConfig, DuplicatePolicy},
+    http::HttpConfig,
+    purger::{Purger, PurgerConfig},
+    retry::RetryPolicy,
+    spotify::SpotifyOAuth2Client,
+    state::*,
+    token::OAuth2Token,
+    twitter::TwitterOAuth2Client,
+    verifier::{OAuth2Provider, OAuth2VerifierConfig, OAuth2Verifiers},
+};
@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::Utc;
+use serde::Deserialize;
+
+use crate::{services::UserService, AppState};
+
+#[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(default)]
+pub struct PurgerConfig {
+    /// 退会してから完全に消すまでの日数
+    pub retention_days: u32,
+    /// 消す対象を探す間隔 (秒)
+    pub interval_secs: u64,
+}
+
+impl Default for PurgerConfig {
+    fn default() -> PurgerConfig {
+        PurgerConfig {
+            retention_days: 30,
+            interval_secs: 60 * 60,
+        }
+    }
+}
+
+/// 保持期間が過ぎた退会済みのユーザーを定期的に消す
+#[derive(Clone, Debug)]
+pub struct Purger {
+    state: AppState,
+    config: PurgerConfig,
+}
+
+impl Purger {
+    pub fn new(state: AppState, config: PurgerConfig) -> Purger {
+        Purger { state, config }
+    }
+
+    pub async fn run(self) {
+        let mut interval = tokio::time::interval(Duration::from_secs(self.config.interval_secs));
+        loop {
+            interval.tick().await;
+            match self.purge().await {
+                Ok(0) => {}
+                Ok(count) => println!("purged {count} deleted users"),
+                Err(e) => eprintln!("failed to purge deleted users: {e}"),
+            }
+        }
+    }
+
+    pub async fn purge(&self) -> Result<u64> {
+        let retention = chrono::Duration::days(self.config.retention_days.into());
+        UserService::new(self.state.clone())
+            .purge_deleted(Utc::now() - retention)
+            .await
+    }
+}
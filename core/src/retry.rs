@@ -0,0 +1,199 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    RequestBuilder, Response, StatusCode,
+};
+use serde::Deserialize;
+
+/// Twitterのレートリミットがリセットされる時刻 (UNIX時間)
+const X_RATE_LIMIT_RESET: &str = "x-rate-limit-reset";
+
+/// 429や5xxが返ってきたときのリトライの設定
+#[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// 最大のリトライ回数
+    pub max_retries: u32,
+    /// 最初のリトライまでの待ち時間 (ミリ秒)
+    pub base_delay_ms: u64,
+    /// これより長く待つ必要があるときは諦める (秒)
+    pub max_delay_secs: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 3,
+            base_delay_ms: 500,
+            max_delay_secs: 60,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// `attempt` 回目のリトライまでの待ち時間を返す。リトライしないときは `None`
+    ///
+    /// `retry_after` があればそれに従い、なければジッター付きの指数バックオフにする。
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+        let max_delay = Duration::from_secs(self.max_delay_secs);
+        if let Some(retry_after) = retry_after {
+            return (retry_after <= max_delay).then_some(retry_after);
+        }
+        let backoff = Duration::from_millis(self.base_delay_ms)
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(max_delay);
+        let jitter = rand::thread_rng().gen_range(0.0..=0.5);
+        Some(backoff.mul_f64(0.5 + jitter))
+    }
+
+    /// リトライしながらリクエストを送る
+    ///
+    /// 429と5xxのときはリトライして、それでもだめならそのままレスポンスを返す。
+    pub async fn send(&self, req: RequestBuilder) -> reqwest::Result<Response> {
+        self.send_with(req, true).await
+    }
+
+    /// 2回送ると結果が変わるリクエストを送る
+    ///
+    /// 5xxやタイムアウトでは処理されたか分からないので、429と接続できなかったときだけリトライする。
+    pub async fn send_non_idempotent(&self, req: RequestBuilder) -> reqwest::Result<Response> {
+        self.send_with(req, false).await
+    }
+
+    async fn send_with(&self, req: RequestBuilder, idempotent: bool) -> reqwest::Result<Response> {
+        let mut attempt = 0;
+        loop {
+            // bodyがストリームのときは複製できないので一度だけ送る
+            let Some(cloned) = req.try_clone() else {
+                return req.send().await;
+            };
+            let delay = match cloned.send().await {
+                Ok(res) if is_retryable(res.status(), idempotent) => {
+                    match self.delay(attempt, retry_after(res.headers())) {
+                        Some(delay) => delay,
+                        None => return Ok(res),
+                    }
+                }
+                Err(e) if e.is_connect() || (idempotent && e.is_timeout()) => {
+                    match self.delay(attempt, None) {
+                        Some(delay) => delay,
+                        None => return Err(e),
+                    }
+                }
+                result => return result,
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode, idempotent: bool) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || (idempotent && status.is_server_error())
+}
+
+/// `Retry-After` か `x-rate-limit-reset` から待つべき時間を求める
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    if let Some(secs) = header_u64(headers, RETRY_AFTER.as_str()) {
+        return Some(Duration::from_secs(secs));
+    }
+    let reset = header_u64(headers, X_RATE_LIMIT_RESET)?;
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(Duration::from_secs(reset.saturating_sub(now)))
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use reqwest::header::HeaderValue;
+
+    use super::*;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_retries: 10,
+            base_delay_ms: 1000,
+            max_delay_secs: 5,
+        }
+    }
+
+    #[test]
+    fn delay_with_backoff() {
+        let policy = policy();
+        // (attempt, ジッターなしの待ち時間)
+        for (attempt, backoff) in [(0, 1000), (1, 2000), (2, 4000), (3, 5000), (9, 5000)] {
+            let delay = policy.delay(attempt, None).expect("retries");
+            assert!(
+                Duration::from_millis(backoff / 2) <= delay
+                    && delay <= Duration::from_millis(backoff),
+                "attempt {attempt}: {delay:?}"
+            );
+        }
+        assert_eq!(policy.delay(10, None), None);
+    }
+
+    #[test]
+    fn delay_with_retry_after() {
+        let policy = policy();
+        let secs = Duration::from_secs;
+        assert_eq!(policy.delay(0, Some(secs(3))), Some(secs(3)));
+        assert_eq!(policy.delay(9, Some(secs(5))), Some(secs(5)));
+        // 長すぎるときとリトライし尽くしたときは諦める
+        assert_eq!(policy.delay(0, Some(secs(6))), None);
+        assert_eq!(policy.delay(10, Some(secs(1))), None);
+    }
+
+    #[test]
+    fn parse_retry_after() {
+        let headers = |pairs: &[(&'static str, String)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in pairs {
+                headers.insert(*name, HeaderValue::from_str(value).expect("valid header"));
+            }
+            headers
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .expect("after epoch")
+            .as_secs();
+
+        let retry_after_7 = headers(&[("retry-after", "7".to_string())]);
+        assert_eq!(retry_after(&retry_after_7), Some(Duration::from_secs(7)));
+        // HTTP-dateの形式には対応していない
+        let http_date = headers(&[("retry-after", "Wed, 21 Oct 2015 07:28:00 GMT".to_string())]);
+        assert_eq!(retry_after(&http_date), None);
+        // Retry-Afterの方を優先する
+        let both = headers(&[
+            ("retry-after", "7".to_string()),
+            (X_RATE_LIMIT_RESET, (now + 30).to_string()),
+        ]);
+        assert_eq!(retry_after(&both), Some(Duration::from_secs(7)));
+
+        let reset = headers(&[(X_RATE_LIMIT_RESET, (now + 30).to_string())]);
+        let delay = retry_after(&reset).expect("reset is parsed");
+        assert!(
+            Duration::from_secs(29) <= delay && delay <= Duration::from_secs(30),
+            "{delay:?}"
+        );
+        let past = headers(&[(X_RATE_LIMIT_RESET, (now - 30).to_string())]);
+        assert_eq!(retry_after(&past), Some(Duration::ZERO));
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn retry_non_idempotent_only_on_429() {
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS, true));
+        assert!(is_retryable(StatusCode::SERVICE_UNAVAILABLE, true));
+        assert!(!is_retryable(StatusCode::BAD_REQUEST, true));
+        assert!(is_retryable(StatusCode::TOO_MANY_REQUESTS, false));
+        assert!(!is_retryable(StatusCode::SERVICE_UNAVAILABLE, false));
+    }
+}
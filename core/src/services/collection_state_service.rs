@@ -0,0 +1,123 @@
+use anyhow::Result;
+use chrono::Utc;
+use entity::collection_state;
+use sea_orm::{ActiveModelTrait, ActiveValue, DatabaseConnection, EntityTrait, Set};
+
+use crate::AppState;
+
+/// Twitterのアカウントごとの収集の状態
+#[derive(Clone, Debug)]
+pub struct CollectionStateService {
+    state: AppState,
+}
+
+impl CollectionStateService {
+    pub fn new(state: AppState) -> CollectionStateService {
+        CollectionStateService { state }
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.state.connection
+    }
+
+    pub async fn find(&self, twitter_account_id: &str) -> Result<Option<collection_state::Model>> {
+        let state = collection_state::Entity::find_by_id(twitter_account_id.to_string())
+            .one(self.connection())
+            .await?;
+        Ok(state)
+    }
+
+    /// 前回読んだところ
+    pub async fn find_cursor(&self, twitter_account_id: &str) -> Result<Option<String>> {
+        let state = self.find(twitter_account_id).await?;
+        Ok(state.and_then(|state| state.newest_tweet_id))
+    }
+
+    /// なければ作ってから `update` で書き換える
+    async fn upsert(
+        &self,
+        twitter_account_id: &str,
+        update: impl FnOnce(&mut collection_state::ActiveModel),
+    ) -> Result<()> {
+        match self.find(twitter_account_id).await? {
+            Some(state) => {
+                let mut state: collection_state::ActiveModel = state.into();
+                update(&mut state);
+                state.updated_at = Set(Utc::now().into());
+                state.update(self.connection()).await?;
+            }
+            None => {
+                let mut state = collection_state::ActiveModel {
+                    twitter_account_id: Set(twitter_account_id.to_string()),
+                    newest_tweet_id: Set(None),
+                    last_run_at: Set(None),
+                    last_success_at: Set(None),
+                    consecutive_failures: Set(0),
+                    last_error: Set(None),
+                    updated_at: Set(Utc::now().into()),
+                };
+                update(&mut state);
+                state.insert(self.connection()).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 読んだところを保存する。`None` なら前回のままにする
+    pub async fn save_cursor(
+        &self,
+        twitter_account_id: &str,
+        newest_tweet_id: Option<String>,
+    ) -> Result<()> {
+        let Some(newest_tweet_id) = newest_tweet_id else {
+            return Ok(());
+        };
+        self.upsert(twitter_account_id, |state| {
+            state.newest_tweet_id = Set(Some(newest_tweet_id));
+        })
+        .await
+    }
+
+    /// 読んだところを忘れて、次回は最新のページから読み直す
+    pub async fn reset_cursor(&self, twitter_account_id: &str) -> Result<()> {
+        if self.find(twitter_account_id).await?.is_none() {
+            return Ok(());
+        }
+        self.upsert(twitter_account_id, |state| {
+            state.newest_tweet_id = Set(None);
+        })
+        .await
+    }
+
+    /// 収集に成功したことを記録する
+    pub async fn record_success(&self, twitter_account_id: &str) -> Result<()> {
+        self.upsert(twitter_account_id, |state| {
+            state.last_run_at = Set(Some(Utc::now().into()));
+            state.last_success_at = Set(Some(Utc::now().into()));
+            state.consecutive_failures = Set(0);
+            state.last_error = Set(None);
+        })
+        .await
+    }
+
+    /// 収集に失敗したことを記録する
+    pub async fn record_failure(&self, twitter_account_id: &str, error: String) -> Result<()> {
+        self.upsert(twitter_account_id, |state| {
+            let failures = match &state.consecutive_failures {
+                ActiveValue::Set(n) | ActiveValue::Unchanged(n) => *n,
+                ActiveValue::NotSet => 0,
+            };
+            state.last_run_at = Set(Some(Utc::now().into()));
+            state.consecutive_failures = Set(failures + 1);
+            state.last_error = Set(Some(error));
+        })
+        .await
+    }
+
+    pub async fn delete(&self, twitter_account_id: &str) -> Result<()> {
+        collection_state::Entity::delete_by_id(twitter_account_id.to_string())
+            .exec(self.connection())
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::Result;
+use chrono::Utc;
+use entity::filter_rule;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, QueryOrder, Set,
+    TryIntoModel,
+};
+
+use crate::{
+    twitter::{FilterRule, TweetFilter},
+    AppState,
+};
+
+/// ユーザーごとのツイートを拾うかどうかのルール
+#[derive(Clone, Debug)]
+pub struct FilterService {
+    state: AppState,
+}
+
+impl FilterService {
+    pub fn new(state: AppState) -> FilterService {
+        FilterService { state }
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.state.connection
+    }
+
+    pub async fn find_by_user(&self, owner_user_id: i32) -> Result<Vec<filter_rule::Model>> {
+        let rules = filter_rule::Entity::find()
+            .filter(filter_rule::Column::OwnerUserId.eq(owner_user_id))
+            .order_by_asc(filter_rule::Column::Id)
+            .all(self.connection())
+            .await?;
+        Ok(rules)
+    }
+
+    pub async fn create(&self, owner_user_id: i32, rule: FilterRule) -> Result<filter_rule::Model> {
+        let FilterRule { kind, value } = rule.normalize()?;
+        let rule = filter_rule::ActiveModel {
+            owner_user_id: Set(owner_user_id),
+            kind: Set(kind.as_str().to_string()),
+            value: Set(value),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        }
+        .insert(self.connection())
+        .await?
+        .try_into_model()?;
+        Ok(rule)
+    }
+
+    /// 消したものがなければ `false`
+    pub async fn delete(&self, owner_user_id: i32, id: i32) -> Result<bool> {
+        let result = filter_rule::Entity::delete_many()
+            .filter(filter_rule::Column::OwnerUserId.eq(owner_user_id))
+            .filter(filter_rule::Column::Id.eq(id))
+            .exec(self.connection())
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    /// 収集に使うフィルタ。読めないルールは無視する
+    pub async fn tweet_filter(&self, owner_user_id: i32) -> Result<TweetFilter> {
+        let rules = self
+            .find_by_user(owner_user_id)
+            .await?
+            .into_iter()
+            .filter_map(|rule| match rule.kind.parse() {
+                Ok(kind) => Some(FilterRule {
+                    kind,
+                    value: rule.value,
+                }),
+                Err(e) => {
+                    eprintln!("{e}");
+                    None
+                }
+            })
+            .collect();
+        Ok(TweetFilter::new(rules))
+    }
+}
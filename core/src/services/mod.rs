@@ -1,4 +1,21 @@
+mod collection_state_service;
+mod filter_service;
+mod playlist_service;
+mod source_service;
+mod token_service;
+mod track_service;
 mod twitter_oauth2_service;
 mod user_service;
 
-pub use self::{twitter_oauth2_service::TwitterOAuth2Service, user_service::UserService};
+pub use self::{
+    collection_state_service::CollectionStateService,
+    filter_service::FilterService,
+    playlist_service::{PlaylistNotSelectable, PlaylistService, SpotifyAccountNotFound},
+    source_service::{SourceService, TwitterAccountNotFound},
+    token_service::TokenService,
+    track_service::TrackService,
+    twitter_oauth2_service::{
+        OAuth2VerifierMismatch, TwitterAccountConflict, TwitterOAuth2Service,
+    },
+    user_service::{UserDeleted, UserNotFound, UserProfile, UserService},
+};
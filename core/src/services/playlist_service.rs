@@ -0,0 +1,162 @@
+use std::future::Future;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use entity::{spotify_account, user};
+use sea_orm::{ActiveModelTrait, ColumnTrait, DatabaseConnection, QueryFilter, Set, TryIntoModel};
+
+use crate::{
+    services::{TokenService, UserNotFound, UserService},
+    spotify::{SimplifiedPlaylist, SimplifiedPlaylists, SpotifyApiError, SpotifyClient},
+    AppState,
+};
+
+const PLAYLIST_NAME: &str = "mikage";
+const PLAYLIST_DESCRIPTION: &str = "Tracks collected from Twitter by mikage";
+
+/// ユーザーがSpotifyを連携していない
+#[derive(thiserror::Error, Debug)]
+#[error("spotify account of user {user_id} is not found")]
+pub struct SpotifyAccountNotFound {
+    pub user_id: i32,
+}
+
+/// 自分のものでも共同編集でもないプレイリストは追加先にできない
+#[derive(thiserror::Error, Debug)]
+#[error("playlist {playlist_id} is not owned by the user nor collaborative")]
+pub struct PlaylistNotSelectable {
+    pub playlist_id: String,
+}
+
+/// 収集した楽曲を追加するプレイリストを管理する
+#[derive(Clone, Debug)]
+pub struct PlaylistService {
+    user: user::Model,
+    state: AppState,
+}
+
+impl PlaylistService {
+    pub fn new(user: user::Model, state: AppState) -> PlaylistService {
+        PlaylistService { user, state }
+    }
+
+    pub async fn new_with_user_id(state: AppState, id: i32) -> Result<PlaylistService> {
+        let user = UserService::new(state.clone()).find_active_user(id).await?;
+        let Some(user) = user else {
+            bail!(UserNotFound { user_id: id });
+        };
+        Ok(PlaylistService::new(user, state))
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.state.connection
+    }
+
+    pub async fn spotify_account(&self) -> Result<spotify_account::Model> {
+        let account = UserService::linked_spotify_accounts()
+            .filter(spotify_account::Column::OwnerUserId.eq(self.user.id))
+            .one(self.connection())
+            .await?;
+        let Some(account) = account else {
+            bail!(SpotifyAccountNotFound {
+                user_id: self.user.id
+            });
+        };
+        Ok(account)
+    }
+
+    /// 今設定されているプレイリスト
+    pub async fn current(&self) -> Result<Option<SimplifiedPlaylist>> {
+        let account = self.spotify_account().await?;
+        let Some(playlist_id) = &account.playlist_id else {
+            return Ok(None);
+        };
+        let playlist = self
+            .call_spotify(&account, |client| async move {
+                client.get_playlist(playlist_id).await
+            })
+            .await?;
+        Ok(Some(playlist))
+    }
+
+    /// 選択できるユーザーのプレイリスト
+    pub async fn candidates(&self) -> Result<SimplifiedPlaylists> {
+        let account = self.spotify_account().await?;
+        let mut playlists = self
+            .call_spotify(&account, |client| async move {
+                client.get_current_users_playlists().await
+            })
+            .await?;
+        playlists
+            .items
+            .retain(|playlist| is_selectable(&account, playlist));
+        Ok(playlists)
+    }
+
+    /// mikage用のプレイリストを作成して設定する
+    pub async fn create(&self) -> Result<SimplifiedPlaylist> {
+        let account = self.spotify_account().await?;
+        let user_id = &account.user_id;
+        let playlist = self
+            .call_spotify(&account, |client| async move {
+                client
+                    .create_playlist(user_id, PLAYLIST_NAME, PLAYLIST_DESCRIPTION, false)
+                    .await
+            })
+            .await?;
+        self.save(account, &playlist).await?;
+        Ok(playlist)
+    }
+
+    /// 既存のプレイリストを設定する
+    pub async fn select(&self, playlist_id: &str) -> Result<SimplifiedPlaylist> {
+        let account = self.spotify_account().await?;
+        let playlist = self
+            .call_spotify(&account, |client| async move {
+                client.get_playlist(playlist_id).await
+            })
+            .await?;
+        if !is_selectable(&account, &playlist) {
+            bail!(PlaylistNotSelectable {
+                playlist_id: playlist.id
+            });
+        }
+        self.save(account, &playlist).await?;
+        Ok(playlist)
+    }
+
+    /// Spotifyのクライアントで `call` を呼び、401を返されたらリフレッシュしてもう一度だけ試す
+    async fn call_spotify<T, F, Fut>(&self, account: &spotify_account::Model, call: F) -> Result<T>
+    where
+        F: Fn(SpotifyClient) -> Fut,
+        Fut: Future<Output = Result<T, SpotifyApiError>>,
+    {
+        let tokens = TokenService::new(self.state.clone());
+        let client = tokens.spotify_client(account).await?;
+        match call(client).await {
+            Err(e) if e.is_unauthorized() => {
+                let account = tokens.refresh_spotify_account(account).await?;
+                let client = self.state.spotify_client(account.access_token);
+                Ok(call(client).await?)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    async fn save(
+        &self,
+        account: spotify_account::Model,
+        playlist: &SimplifiedPlaylist,
+    ) -> Result<spotify_account::Model> {
+        let mut account: spotify_account::ActiveModel = account.into();
+        account.playlist_id = Set(Some(playlist.id.clone()));
+        account.updated_at = Set(Utc::now().into());
+        let account = account.save(self.connection()).await?.try_into_model()?;
+        Ok(account)
+    }
+}
+
+/// 楽曲を追加できるプレイリストか
+fn is_selectable(account: &spotify_account::Model, playlist: &SimplifiedPlaylist) -> bool {
+    playlist.owner.id == account.user_id || playlist.collaborative
+}
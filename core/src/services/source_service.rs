@@ -0,0 +1,81 @@
+use anyhow::{bail, Result};
+use chrono::Utc;
+use entity::{twitter_account, user};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter, Set, TryIntoModel,
+};
+
+use crate::{
+    services::{CollectionStateService, UserNotFound, UserService},
+    twitter::TweetSource,
+    AppState,
+};
+
+/// ユーザーがTwitterを連携していない
+#[derive(thiserror::Error, Debug)]
+#[error("twitter account of user {user_id} is not found")]
+pub struct TwitterAccountNotFound {
+    pub user_id: i32,
+}
+
+/// ツイートを集めるところを管理する
+#[derive(Clone, Debug)]
+pub struct SourceService {
+    user: user::Model,
+    state: AppState,
+}
+
+impl SourceService {
+    pub fn new(user: user::Model, state: AppState) -> SourceService {
+        SourceService { user, state }
+    }
+
+    pub async fn new_with_user_id(state: AppState, id: i32) -> Result<SourceService> {
+        let user = UserService::new(state.clone()).find_active_user(id).await?;
+        let Some(user) = user else {
+            bail!(UserNotFound { user_id: id });
+        };
+        Ok(SourceService::new(user, state))
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.state.connection
+    }
+
+    pub async fn twitter_account(&self) -> Result<twitter_account::Model> {
+        let account = twitter_account::Entity::find()
+            .filter(twitter_account::Column::OwnerUserId.eq(self.user.id))
+            .one(self.connection())
+            .await?;
+        let Some(account) = account else {
+            bail!(TwitterAccountNotFound {
+                user_id: self.user.id
+            });
+        };
+        Ok(account)
+    }
+
+    /// 今設定されているところ
+    pub async fn current(&self) -> Result<TweetSource> {
+        let account = self.twitter_account().await?;
+        TweetSource::from_parts(&account.source_kind, account.source_id.as_deref())
+    }
+
+    /// 集めるところを変える
+    ///
+    /// 前回読んだところは別のタイムラインのものなので、次回は最新のページから読み直す。
+    pub async fn select(&self, source: TweetSource) -> Result<TweetSource> {
+        source.validate()?;
+        let account = self.twitter_account().await?;
+        let twitter_account_id = account.user_id.clone();
+        let mut account: twitter_account::ActiveModel = account.into();
+        account.source_kind = Set(source.kind().to_string());
+        account.source_id = Set(source.id().map(ToString::to_string));
+        account.updated_at = Set(Utc::now().into());
+        let account = account.save(self.connection()).await?.try_into_model()?;
+        CollectionStateService::new(self.state.clone())
+            .reset_cursor(&twitter_account_id)
+            .await?;
+        TweetSource::from_parts(&account.source_kind, account.source_id.as_deref())
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use entity::{spotify_account, twitter_account};
+use reqwest::StatusCode;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, ActiveModelTrait, DatabaseConnection, Set, TryIntoModel,
+};
+
+use crate::{
+    spotify::{SpotifyApiError, SpotifyClient},
+    twitter::{TimelineReader, TweetSource, TwitterApiError},
+    AppState, OAuth2Token,
+};
+
+/// 期限切れの少し前からリフレッシュしておく
+const EXPIRY_MARGIN_SECS: i64 = 60;
+
+/// 保存されているアクセストークンの期限を見て、必要であればリフレッシュする
+#[derive(Clone, Debug)]
+pub struct TokenService {
+    state: AppState,
+}
+
+impl TokenService {
+    pub fn new(state: AppState) -> TokenService {
+        TokenService { state }
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.state.connection
+    }
+
+    /// 401を返されたエラーか
+    pub fn is_unauthorized(e: &anyhow::Error) -> bool {
+        e.chain().any(|cause| {
+            if let Some(e) = cause.downcast_ref::<SpotifyApiError>() {
+                return e.is_unauthorized();
+            }
+            if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                return e.status() == Some(StatusCode::UNAUTHORIZED);
+            }
+            if let Some(e) = cause.downcast_ref::<TwitterApiError>() {
+                return e.is_unauthorized();
+            }
+            false
+        })
+    }
+
+    pub async fn spotify_client(&self, account: &spotify_account::Model) -> Result<SpotifyClient> {
+        let account = if is_expired(account.expires_at) {
+            self.refresh_spotify_account(account).await?
+        } else {
+            account.clone()
+        };
+        Ok(self.state.spotify_client(account.access_token))
+    }
+
+    pub async fn timeline_reader(
+        &self,
+        account: &twitter_account::Model,
+    ) -> Result<TimelineReader> {
+        let account = if is_expired(account.expires_at) {
+            self.refresh_twitter_account(account).await?
+        } else {
+            account.clone()
+        };
+        let source = TweetSource::from_parts(&account.source_kind, account.source_id.as_deref())?;
+        TimelineReader::new(self.state.twitter_client(account.access_token), source).await
+    }
+
+    pub async fn refresh_spotify_account(
+        &self,
+        account: &spotify_account::Model,
+    ) -> Result<spotify_account::Model> {
+        let client = self.state.spotify_oauth2_client()?;
+        let OAuth2Token {
+            access_token,
+            refresh_token,
+            expires_at,
+        } = client
+            .exchange_refresh_token(account.refresh_token.clone())
+            .await?;
+
+        let mut account: spotify_account::ActiveModel = account.clone().into();
+        account.access_token = Set(access_token);
+        // Spotifyはリフレッシュトークンを返さないことがある
+        if let Some(refresh_token) = refresh_token {
+            account.refresh_token = Set(refresh_token);
+        }
+        account.expires_at = Set(expires_at.map(Into::into));
+        account.updated_at = Set(Utc::now().into());
+        let account = account.save(self.connection()).await?.try_into_model()?;
+        Ok(account)
+    }
+
+    pub async fn refresh_twitter_account(
+        &self,
+        account: &twitter_account::Model,
+    ) -> Result<twitter_account::Model> {
+        let client = self.state.twitter_oauth2_client()?;
+        let OAuth2Token {
+            access_token,
+            refresh_token,
+            expires_at,
+        } = client
+            .exchange_refresh_token(account.refresh_token.clone())
+            .await?;
+
+        let mut account: twitter_account::ActiveModel = account.clone().into();
+        account.access_token = Set(access_token);
+        // Twitterはリフレッシュトークンもローテーションされる
+        if let Some(refresh_token) = refresh_token {
+            account.refresh_token = Set(refresh_token);
+        }
+        account.expires_at = Set(expires_at.map(Into::into));
+        account.updated_at = Set(Utc::now().into());
+        let account = account.save(self.connection()).await?.try_into_model()?;
+        Ok(account)
+    }
+}
+
+fn is_expired(expires_at: Option<DateTimeWithTimeZone>) -> bool {
+    // 期限が分からないものは401が返ってきたときにリフレッシュする
+    let Some(expires_at) = expires_at else {
+        return false;
+    };
+    expires_at <= Utc::now() + Duration::seconds(EXPIRY_MARGIN_SECS)
+}
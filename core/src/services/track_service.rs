@@ -0,0 +1,102 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use entity::track;
+use reqwest::Url;
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, Set, TryIntoModel,
+};
+
+use crate::{
+    twitter::{Tweet, TweetReferenceKind},
+    AppState,
+};
+
+#[derive(Clone, Debug)]
+pub struct TrackService {
+    state: AppState,
+}
+
+impl TrackService {
+    pub fn new(state: AppState) -> TrackService {
+        TrackService { state }
+    }
+
+    pub fn connection(&self) -> &DatabaseConnection {
+        &self.state.connection
+    }
+
+    /// ツイートから拾った楽曲を記録する
+    ///
+    /// `track_url` が引用したツイートのものなら、引用したツイートも記録する。
+    pub async fn insert(
+        &self,
+        owner_user_id: i32,
+        track_uri: String,
+        track_url: &Url,
+        tweet: &Tweet,
+    ) -> Result<track::Model> {
+        let source_url = format!("https://twitter.com/{}/status/{}", tweet.username, tweet.id);
+        let quoted_tweet_id = if tweet.urls.contains(track_url) {
+            None
+        } else {
+            tweet
+                .referenced_tweets
+                .iter()
+                .find(|reference| reference.kind == TweetReferenceKind::Quoted)
+                .map(|reference| reference.id.to_string())
+        };
+        let tweet_media_urls = if tweet.media_urls.is_empty() {
+            None
+        } else {
+            let urls = tweet.media_urls.iter().map(Url::as_str).collect::<Vec<_>>();
+            Some(serde_json::to_string(&urls)?)
+        };
+        let track = track::ActiveModel {
+            owner_user_id: Set(owner_user_id),
+            track_uri: Set(track_uri),
+            track_url: Set(track_url.to_string()),
+            source_url: Set(source_url),
+            tweet_id: Set(tweet.id.to_string()),
+            tweet_author_id: Set(tweet.author_id.to_string()),
+            tweet_author_username: Set(tweet.username.clone()),
+            tweet_author_name: Set(Some(tweet.author_name.clone())),
+            tweet_created_at: Set(tweet.created_at.map(Into::into)),
+            quoted_tweet_id: Set(quoted_tweet_id),
+            tweet_media_urls: Set(tweet_media_urls),
+            created_at: Set(Utc::now().into()),
+            ..Default::default()
+        }
+        .insert(self.connection())
+        .await?
+        .try_into_model()?;
+        Ok(track)
+    }
+
+    /// ユーザーが拾った楽曲を新しい順に取得する
+    pub async fn find_by_user(&self, owner_user_id: i32) -> Result<Vec<track::Model>> {
+        let tracks = track::Entity::find()
+            .filter(track::Column::OwnerUserId.eq(owner_user_id))
+            .order_by_desc(track::Column::CreatedAt)
+            .all(self.connection())
+            .await?;
+        Ok(tracks)
+    }
+
+    /// ユーザーが `since` 以降にその楽曲を拾っているか
+    pub async fn exists_since(
+        &self,
+        owner_user_id: i32,
+        track_uri: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<bool> {
+        let mut query = track::Entity::find()
+            .filter(track::Column::OwnerUserId.eq(owner_user_id))
+            .filter(track::Column::TrackUri.eq(track_uri));
+        if let Some(since) = since {
+            query = query.filter(track::Column::CreatedAt.gte(since));
+        }
+        let count = query.count(self.connection()).await?;
+        Ok(count > 0)
+    }
+}
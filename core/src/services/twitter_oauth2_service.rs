@@ -1,11 +1,32 @@
+use crate::{
+    services::{CollectionStateService, UserNotFound, UserService},
+    twitter::TweetSource,
+    AppState, OAuth2Token, TwitterOAuth2Client,
+};
 use anyhow::{bail, Result};
 use chrono::Utc;
 use entity::{twitter_account, user};
 use reqwest::Url;
-use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set, TryIntoModel};
-use twitter_v2::{authorization::BearerToken, TwitterApi};
+use sea_orm::{
+    ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait, ModelTrait, QueryFilter, Set,
+    TryIntoModel,
+};
 
-use crate::{AppState, TwitterOAuth2Client};
+/// 連携しようとしたTwitterのアカウントが既に他のユーザーに紐付いている
+#[derive(thiserror::Error, Debug)]
+#[error("twitter account {twitter_user_id} is already linked to user {owner_user_id}")]
+pub struct TwitterAccountConflict {
+    pub twitter_user_id: String,
+    pub owner_user_id: i32,
+}
+
+/// 他のユーザーが始めた認可のコールバックが来た
+#[derive(thiserror::Error, Debug)]
+#[error("oauth2 verifier was created by user {created_by:?}, not user {user_id}")]
+pub struct OAuth2VerifierMismatch {
+    pub user_id: i32,
+    pub created_by: Option<i32>,
+}
 
 #[derive(Clone, Debug)]
 pub struct TwitterOAuth2Service {
@@ -19,11 +40,10 @@ impl TwitterOAuth2Service {
     }
 
     pub async fn new_with_user_id(state: AppState, id: i32) -> Result<TwitterOAuth2Service> {
-        let user = user::Entity::find_by_id(id).one(&state.connection).await?;
+        let user = UserService::new(state.clone()).find_active_user(id).await?;
         let Some(user) = user else {
-            bail!("User not found");
+            bail!(UserNotFound { user_id: id });
         };
-        let user = user.try_into_model()?;
         Ok(TwitterOAuth2Service::new(user, state))
     }
 
@@ -32,55 +52,104 @@ impl TwitterOAuth2Service {
     }
 
     pub fn twitter_oauth2_client(&self) -> Result<TwitterOAuth2Client> {
-        TwitterOAuth2Client::new(
-            self.state
-                .oauth2_client_credentials
-                .twitter
-                .client_id
-                .clone(),
-            self.state
-                .oauth2_client_credentials
-                .twitter
-                .client_secret
-                .clone(),
-            self.state
-                .oauth2_client_credentials
-                .twitter
-                .redirect_uri
-                .clone(),
-        )
+        self.state.twitter_oauth2_client()
     }
 
-    pub fn create_twitter_redirect_url(&self) -> Result<Url> {
+    /// 認可画面のURLと、セッションに保存しておく `state` を返す
+    pub async fn create_twitter_redirect_url(&self) -> Result<(Url, String)> {
         let client = self.twitter_oauth2_client()?;
         let (url, state, verifier) = client.create_authorize_urls();
-        self.state.twitter_verifiers.insert(state, verifier)?;
-        Ok(url)
+        self.state
+            .twitter_verifiers
+            .insert(state.clone(), verifier, Some(self.user.id))
+            .await?;
+        Ok((url, state))
     }
 
+    /// 他のユーザーが始めた認可なら [`OAuth2VerifierMismatch`] を、
+    /// 他のユーザーに紐付いているアカウントなら [`TwitterAccountConflict`] を返す
+    ///
+    /// `transfer` が `true` なら、認可し直したものとしてこのユーザーに付け替える。
     pub async fn exchange_spotify_code(
         &self,
         code: String,
         state: String,
+        transfer: bool,
     ) -> Result<twitter_account::Model> {
-        let verifier = self.state.twitter_verifiers.remove(&state)?;
+        let verifier = self.state.twitter_verifiers.remove(&state).await?;
+        // 他のユーザーが始めた認可で連携させない
+        if verifier.user_id != Some(self.user.id) {
+            bail!(OAuth2VerifierMismatch {
+                user_id: self.user.id,
+                created_by: verifier.user_id,
+            });
+        }
         let client = self.twitter_oauth2_client()?;
-        let (access_token, refresh_token) = client.exchange_code(verifier, code).await?;
+        let OAuth2Token {
+            access_token,
+            refresh_token,
+            expires_at,
+        } = client.exchange_code(verifier.verifier, code).await?;
         let Some(refresh_token) = refresh_token else {
             bail!("refresh_token is none");
         };
 
-        let api = TwitterApi::new(BearerToken::new(access_token.clone()));
-        let user = api.get_users_me().send().await?;
-        let Some(user) = user.into_data() else {
-            bail!("not include user data");
-        };
+        let user = self
+            .state
+            .twitter_client(access_token.clone())
+            .get_users_me()
+            .await?;
         let user_id = user.id.as_u64().to_string();
         let avatar_url = user
             .profile_image_url
             .map(|url| url.to_string())
             .unwrap_or_default();
 
+        // 連携し直したときはトークンとプロフィールを更新する
+        if let Some(twitter) = twitter_account::Entity::find_by_id(user_id.clone())
+            .one(self.connection())
+            .await?
+        {
+            let transferred = twitter.owner_user_id != self.user.id;
+            if transferred && !transfer {
+                // 発行されたトークンは使わないので失効させておく
+                if let Err(e) = client.revoke_refresh_token(refresh_token).await {
+                    eprintln!(
+                        "failed to revoke twitter token for {}: {e}",
+                        twitter.user_id
+                    );
+                }
+                return Err(TwitterAccountConflict {
+                    twitter_user_id: twitter.user_id,
+                    owner_user_id: twitter.owner_user_id,
+                }
+                .into());
+            }
+            let mut twitter: twitter_account::ActiveModel = twitter.into();
+            twitter.screen_name = Set(user.username);
+            twitter.display_name = Set(user.name);
+            twitter.access_token = Set(access_token);
+            twitter.refresh_token = Set(refresh_token);
+            twitter.expires_at = Set(expires_at.map(Into::into));
+            twitter.owner_user_id = Set(self.user.id);
+            twitter.updated_at = Set(Utc::now().into());
+            if !avatar_url.is_empty() {
+                twitter.avatar_url = Set(avatar_url);
+            }
+            // 前のユーザーが選んだ取得元や読んだところは引き継がない
+            if transferred {
+                twitter.source_kind = Set(TweetSource::Home.kind().to_string());
+                twitter.source_id = Set(None);
+            }
+            let twitter = twitter.save(self.connection()).await?.try_into_model()?;
+            if transferred {
+                CollectionStateService::new(self.state.clone())
+                    .delete(&twitter.user_id)
+                    .await?;
+            }
+            return Ok(twitter);
+        }
+
         let twitter = twitter_account::ActiveModel {
             user_id: Set(user_id),
             screen_name: Set(user.username),
@@ -88,7 +157,10 @@ impl TwitterOAuth2Service {
             avatar_url: Set(avatar_url),
             access_token: Set(access_token),
             refresh_token: Set(refresh_token),
+            expires_at: Set(expires_at.map(Into::into)),
             owner_user_id: Set(self.user.id),
+            source_kind: Set(TweetSource::Home.kind().to_string()),
+            source_id: Set(None),
             created_at: Set(Utc::now().into()),
             updated_at: Set(Utc::now().into()),
         }
@@ -98,4 +170,32 @@ impl TwitterOAuth2Service {
 
         Ok(twitter)
     }
+
+    /// トークンを失効させてから連携を解除する。解除したものがなければ `false`
+    pub async fn unlink(&self) -> Result<bool> {
+        let accounts = twitter_account::Entity::find()
+            .filter(twitter_account::Column::OwnerUserId.eq(self.user.id))
+            .all(self.connection())
+            .await?;
+        if accounts.is_empty() {
+            return Ok(false);
+        }
+        let client = self.twitter_oauth2_client()?;
+        let states = CollectionStateService::new(self.state.clone());
+        for account in accounts {
+            // 既に失効していることもあるので、失敗しても連携は解除する
+            if let Err(e) = client
+                .revoke_refresh_token(account.refresh_token.clone())
+                .await
+            {
+                eprintln!(
+                    "failed to revoke twitter token for {}: {e}",
+                    account.user_id
+                );
+            }
+            states.delete(&account.user_id).await?;
+            account.delete(self.connection()).await?;
+        }
+        Ok(true)
+    }
 }
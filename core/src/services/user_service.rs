@@ -1,11 +1,42 @@
 use anyhow::{bail, Result};
-use chrono::Utc;
-use entity::{spotify_account, user};
+use chrono::{DateTime, Utc};
+use entity::{collection_state, filter_rule, spotify_account, track, twitter_account, user};
 use reqwest::Url;
-use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set, TryIntoModel};
-use serde::Deserialize;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    ModelTrait, QueryFilter, Select, Set, TryIntoModel,
+};
+use serde::Serialize;
 
-use crate::{AppState, SpotifyOAuth2Client};
+use crate::{
+    services::TwitterOAuth2Service, spotify::CurrentUsersProfile, AppState, OAuth2Token,
+    SpotifyOAuth2Client,
+};
+
+/// 退会したユーザーがログインしようとした
+#[derive(thiserror::Error, Debug)]
+#[error("user {user_id} is deleted")]
+pub struct UserDeleted {
+    pub user_id: i32,
+}
+
+/// セッションに保存されているユーザーがいないか、退会している
+#[derive(thiserror::Error, Debug)]
+#[error("user {user_id} is not found")]
+pub struct UserNotFound {
+    pub user_id: i32,
+}
+
+/// ユーザーと連携しているアカウント。トークンはシリアライズされない
+#[derive(Serialize, Clone, Debug)]
+pub struct UserProfile {
+    #[serde(flatten)]
+    pub user: user::Model,
+    pub spotify_account: Option<spotify_account::Model>,
+    pub twitter_account: Option<twitter_account::Model>,
+    /// 連携しているTwitterのアカウントの収集の状態
+    pub collection_state: Option<collection_state::Model>,
+}
 
 #[derive(Clone, Debug)]
 pub struct UserService {
@@ -22,67 +53,49 @@ impl UserService {
     }
 
     pub fn spotify_oauth2_client(&self) -> Result<SpotifyOAuth2Client> {
-        SpotifyOAuth2Client::new(
-            self.state
-                .oauth2_client_credentials
-                .spotify
-                .client_id
-                .clone(),
-            self.state
-                .oauth2_client_credentials
-                .spotify
-                .client_secret
-                .clone(),
-            self.state
-                .oauth2_client_credentials
-                .spotify
-                .redirect_uri
-                .clone(),
-        )
+        self.state.spotify_oauth2_client()
     }
 
-    pub fn create_spotify_redirect_url(&self) -> Result<Url> {
+    /// 認可画面のURLと、セッションに保存しておく `state` を返す
+    pub async fn create_spotify_redirect_url(&self) -> Result<(Url, String)> {
         let client = self.spotify_oauth2_client()?;
         let (url, state, verifier) = client.create_authorize_urls();
-        self.state.spotify_verifiers.insert(state, verifier)?;
-        Ok(url)
+        self.state
+            .spotify_verifiers
+            .insert(state.clone(), verifier, None)
+            .await?;
+        Ok((url, state))
     }
 
+    /// `current_user_id` はログインしたままSpotifyを連携し直すときのユーザー
+    ///
+    /// 退会したユーザーは `reactivate` が `true` でなければ [`UserDeleted`] を返す。
     pub async fn exchange_spotify_code(
         &self,
         code: String,
         state: String,
+        current_user_id: Option<i32>,
+        reactivate: bool,
     ) -> Result<(user::Model, spotify_account::Model)> {
-        let verifier = self.state.spotify_verifiers.remove(&state)?;
+        let verifier = self.state.spotify_verifiers.remove(&state).await?;
         let client = self.spotify_oauth2_client()?;
-        let (access_token, refresh_token) = client.exchange_code(verifier, code).await?;
+        let OAuth2Token {
+            access_token,
+            refresh_token,
+            expires_at,
+        } = client.exchange_code(verifier.verifier, code).await?;
         let Some(refresh_token) = refresh_token else {
             bail!("refresh_token is none");
         };
 
-        #[derive(Deserialize, Debug)]
-        struct Image {
-            url: String,
-        }
-
-        #[derive(Deserialize, Debug)]
-        struct SpotifyUserResponse {
-            display_name: String,
-            id: String,
-            images: Vec<Image>,
-        }
-
-        let SpotifyUserResponse {
+        let CurrentUsersProfile {
             id: user_id,
             display_name,
             images,
-        } = reqwest::Client::builder()
-            .build()?
-            .get("https://api.spotify.com/v1/me")
-            .bearer_auth(&access_token)
-            .send()
-            .await?
-            .json::<SpotifyUserResponse>()
+        } = self
+            .state
+            .spotify_client(access_token.clone())
+            .get_current_users_profile()
             .await?;
         let avatar_url = images
             .into_iter()
@@ -96,10 +109,12 @@ impl UserService {
                 .one(self.connection())
                 .await
         {
+            let user = self.ensure_active(user, reactivate).await?;
             let mut spotify_account: spotify_account::ActiveModel = spotify_account.into();
             spotify_account.display_name = Set(display_name);
             spotify_account.access_token = Set(access_token);
             spotify_account.refresh_token = Set(refresh_token);
+            spotify_account.expires_at = Set(expires_at.map(Into::into));
             spotify_account.updated_at = Set(Utc::now().into());
             if !avatar_url.is_empty() {
                 spotify_account.avatar_url = Set(avatar_url);
@@ -111,15 +126,22 @@ impl UserService {
             return Ok((user, spotify_account));
         }
 
-        let user = user::ActiveModel {
-            name: Set(display_name.clone()),
-            created_at: Set(Utc::now().into()),
-            updated_at: Set(Utc::now().into()),
-            ..Default::default()
-        }
-        .insert(self.connection())
-        .await?
-        .try_into_model()?;
+        let current_user = match current_user_id {
+            Some(id) => user::Entity::find_by_id(id).one(self.connection()).await?,
+            None => None,
+        };
+        let user = match current_user {
+            Some(user) => self.ensure_active(user, reactivate).await?,
+            None => user::ActiveModel {
+                name: Set(display_name.clone()),
+                created_at: Set(Utc::now().into()),
+                updated_at: Set(Utc::now().into()),
+                ..Default::default()
+            }
+            .insert(self.connection())
+            .await?
+            .try_into_model()?,
+        };
 
         let spotify = spotify_account::ActiveModel {
             user_id: Set(user_id),
@@ -127,7 +149,9 @@ impl UserService {
             avatar_url: Set(avatar_url),
             access_token: Set(access_token),
             refresh_token: Set(refresh_token),
+            expires_at: Set(expires_at.map(Into::into)),
             owner_user_id: Set(user.id),
+            playlist_id: Set(None),
             created_at: Set(Utc::now().into()),
             updated_at: Set(Utc::now().into()),
         }
@@ -137,4 +161,157 @@ impl UserService {
 
         Ok((user, spotify))
     }
+
+    /// 退会したユーザーは `reactivate` が指定されたときだけ復帰させる
+    async fn ensure_active(&self, user: user::Model, reactivate: bool) -> Result<user::Model> {
+        if user.deleted_at.is_none() {
+            return Ok(user);
+        }
+        if !reactivate {
+            return Err(UserDeleted { user_id: user.id }.into());
+        }
+        let mut user: user::ActiveModel = user.into();
+        user.deleted_at = Set(None);
+        user.activated_at = Set(Some(Utc::now().into()));
+        user.updated_at = Set(Utc::now().into());
+        let user = user.save(self.connection()).await?.try_into_model()?;
+        Ok(user)
+    }
+
+    /// 退会する
+    ///
+    /// Twitterのトークンを失効させて連携を解除し、Spotifyのトークンを消して収集を止める。
+    /// ユーザーとSpotifyのアカウントは保持期間が過ぎるまで残す。
+    pub async fn delete_account(&self, user_id: i32) -> Result<()> {
+        let Some(user) = user::Entity::find_by_id(user_id)
+            .one(self.connection())
+            .await?
+        else {
+            bail!("User not found");
+        };
+        TwitterOAuth2Service::new(user.clone(), self.state.clone())
+            .unlink()
+            .await?;
+        if let Some(spotify) = self.find_spotify_account(user.id).await? {
+            self.clear_spotify_tokens(spotify).await?;
+        }
+        let mut user: user::ActiveModel = user.into();
+        user.deleted_at = Set(Some(Utc::now().into()));
+        user.updated_at = Set(Utc::now().into());
+        user.save(self.connection()).await?;
+        Ok(())
+    }
+
+    /// `deleted_before` より前に退会したユーザーを完全に消す
+    pub async fn purge_deleted(&self, deleted_before: DateTime<Utc>) -> Result<u64> {
+        let deleted_before: DateTimeWithTimeZone = deleted_before.into();
+        let users = user::Entity::find()
+            .filter(user::Column::DeletedAt.lt(deleted_before))
+            .all(self.connection())
+            .await?;
+        let count = users.len() as u64;
+        for user in users {
+            track::Entity::delete_many()
+                .filter(track::Column::OwnerUserId.eq(user.id))
+                .exec(self.connection())
+                .await?;
+            filter_rule::Entity::delete_many()
+                .filter(filter_rule::Column::OwnerUserId.eq(user.id))
+                .exec(self.connection())
+                .await?;
+            let twitter_account_ids = twitter_account::Entity::find()
+                .filter(twitter_account::Column::OwnerUserId.eq(user.id))
+                .all(self.connection())
+                .await?
+                .into_iter()
+                .map(|account| account.user_id)
+                .collect::<Vec<_>>();
+            collection_state::Entity::delete_many()
+                .filter(collection_state::Column::TwitterAccountId.is_in(twitter_account_ids))
+                .exec(self.connection())
+                .await?;
+            twitter_account::Entity::delete_many()
+                .filter(twitter_account::Column::OwnerUserId.eq(user.id))
+                .exec(self.connection())
+                .await?;
+            spotify_account::Entity::delete_many()
+                .filter(spotify_account::Column::OwnerUserId.eq(user.id))
+                .exec(self.connection())
+                .await?;
+            user.delete(self.connection()).await?;
+        }
+        Ok(count)
+    }
+
+    /// 連携を解除したSpotifyのアカウントはトークンを消して残しているので除く
+    pub fn linked_spotify_accounts() -> Select<spotify_account::Entity> {
+        spotify_account::Entity::find().filter(spotify_account::Column::RefreshToken.ne(""))
+    }
+
+    pub async fn find_spotify_account(
+        &self,
+        owner_user_id: i32,
+    ) -> Result<Option<spotify_account::Model>> {
+        let account = UserService::linked_spotify_accounts()
+            .filter(spotify_account::Column::OwnerUserId.eq(owner_user_id))
+            .one(self.connection())
+            .await?;
+        Ok(account)
+    }
+
+    /// Spotifyの連携を解除する。Spotifyにはトークンを失効させるAPIがないので消すだけ
+    ///
+    /// ログインし直したときに同じユーザーに戻れるように、アカウントはトークンを消して残す。
+    pub async fn unlink_spotify(&self, owner_user_id: i32) -> Result<bool> {
+        let Some(spotify) = self.find_spotify_account(owner_user_id).await? else {
+            return Ok(false);
+        };
+        self.clear_spotify_tokens(spotify).await?;
+        Ok(true)
+    }
+
+    async fn clear_spotify_tokens(&self, spotify: spotify_account::Model) -> Result<()> {
+        let mut spotify: spotify_account::ActiveModel = spotify.into();
+        spotify.access_token = Set(String::new());
+        spotify.refresh_token = Set(String::new());
+        spotify.expires_at = Set(None);
+        spotify.updated_at = Set(Utc::now().into());
+        spotify.save(self.connection()).await?;
+        Ok(())
+    }
+
+    /// 退会していないユーザー
+    pub async fn find_active_user(&self, user_id: i32) -> Result<Option<user::Model>> {
+        let user = user::Entity::find_by_id(user_id)
+            .filter(user::Column::DeletedAt.is_null())
+            .one(self.connection())
+            .await?;
+        Ok(user)
+    }
+
+    pub async fn find_profile(&self, user_id: i32) -> Result<Option<UserProfile>> {
+        let Some(user) = self.find_active_user(user_id).await? else {
+            return Ok(None);
+        };
+        let spotify_account = self.find_spotify_account(user.id).await?;
+        let twitter_account = user
+            .find_related(twitter_account::Entity)
+            .one(self.connection())
+            .await?;
+        let collection_state = match &twitter_account {
+            Some(twitter_account) => {
+                twitter_account
+                    .find_related(collection_state::Entity)
+                    .one(self.connection())
+                    .await?
+            }
+            None => None,
+        };
+        Ok(Some(UserProfile {
+            user,
+            spotify_account,
+            twitter_account,
+            collection_state,
+        }))
+    }
 }
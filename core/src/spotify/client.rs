@@ -1,17 +1,42 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use derive_new::new;
-use reqwest::RequestBuilder;
-use serde::Deserialize;
+use reqwest::{RequestBuilder, Response};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    spotify::{AddTracksError, SpotifyApiError},
+    RetryPolicy,
+};
+
+pub const SPOTIFY_API_BASE_URL: &str = "https://api.spotify.com/v1";
+/// `GET /playlists/{playlist_id}/tracks` で一度に取得できる最大数
+const PLAYLIST_TRACKS_LIMIT: u32 = 100;
+/// `POST /playlists/{playlist_id}/tracks` で一度に追加できる最大数
+const ADD_TRACKS_LIMIT: usize = 100;
+/// `GET /me/playlists` で一度に取得できる最大数
+const PLAYLISTS_LIMIT: u32 = 50;
 
 #[derive(new, Debug)]
 pub struct SpotifyClient {
     token: String,
+    /// `https://api.spotify.com/v1` など
+    base_url: String,
+    retry: RetryPolicy,
+    client: reqwest::Client,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+pub struct Image {
+    pub url: String,
 }
 
 #[derive(Deserialize, PartialEq, Eq, Debug)]
 pub struct CurrentUsersProfile {
     pub display_name: String,
     pub id: String,
+    #[serde(default)]
+    pub images: Vec<Image>,
 }
 
 #[derive(Deserialize, PartialEq, Eq, Debug)]
@@ -20,70 +45,250 @@ pub struct Track {
     pub uri: String,
 }
 
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+pub struct Episode {
+    pub name: String,
+    pub uri: String,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PlaylistTrack {
+    Track(Track),
+    Episode(Episode),
+}
+
+impl PlaylistTrack {
+    pub fn uri(&self) -> &str {
+        match self {
+            PlaylistTrack::Track(track) => &track.uri,
+            PlaylistTrack::Episode(episode) => &episode.uri,
+        }
+    }
+}
+
 #[derive(Deserialize, PartialEq, Eq, Debug)]
 pub struct PlaylistItem {
-    pub track: Track,
+    /// ローカルファイルや削除された楽曲は `null` になることがある
+    pub track: Option<PlaylistTrack>,
+    pub added_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub is_local: bool,
+}
+
+impl PlaylistItem {
+    pub fn uri(&self) -> Option<&str> {
+        self.track.as_ref().map(PlaylistTrack::uri)
+    }
 }
 
+/// `GET /playlists/{playlist_id}/tracks` の1ページ分
 #[derive(Deserialize, PartialEq, Debug)]
 pub struct Playlist {
     pub items: Vec<PlaylistItem>,
+    pub next: Option<String>,
+    pub total: u32,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+struct SnapshotId {
+    snapshot_id: String,
+}
+
+/// 1回のリクエストで追加できた楽曲と、そのときのプレイリストのスナップショット
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct AddedTracks {
+    pub snapshot_id: String,
+    pub uris: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug)]
+pub struct PlaylistOwner {
+    pub id: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug)]
+pub struct SimplifiedPlaylist {
+    pub id: String,
+    pub name: String,
+    pub owner: PlaylistOwner,
+    #[serde(default)]
+    pub collaborative: bool,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug)]
+pub struct SimplifiedPlaylists {
+    pub items: Vec<SimplifiedPlaylist>,
+}
+
+/// `GET /me/playlists` の1ページ分
+#[derive(Deserialize, PartialEq, Eq, Debug)]
+struct SimplifiedPlaylistsPage {
+    items: Vec<SimplifiedPlaylist>,
+    next: Option<String>,
 }
 
 impl SpotifyClient {
     fn get(&self, path: &str) -> RequestBuilder {
-        reqwest::Client::new()
-            .get(format!("https://api.spotify.com/v1/{path}"))
+        self.client
+            .get(format!("{}/{path}", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.token))
+    }
+
+    fn get_url(&self, url: &str) -> RequestBuilder {
+        self.client
+            .get(url)
             .header("Authorization", format!("Bearer {}", self.token))
     }
 
     fn post(&self, path: &str) -> RequestBuilder {
-        reqwest::Client::new()
-            .post(format!("https://api.spotify.com/v1/{path}"))
+        self.client
+            .post(format!("{}/{path}", self.base_url))
             .header("Authorization", format!("Bearer {}", self.token))
     }
 
-    pub async fn get_playlist_tracks(&self, playlist_id: &str) -> Result<Playlist> {
-        let r = self
-            .get(&format!("playlists/{playlist_id}/tracks"))
-            .send()
-            .await?
-            .json()
-            .await?;
-        Ok(r)
+    async fn send<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T, SpotifyApiError> {
+        read_response(self.retry.send(req).await?).await
+    }
+
+    /// プレイリストの作成や楽曲の追加など、同じものを2回送ってはいけないリクエスト
+    async fn send_non_idempotent<T: DeserializeOwned>(
+        &self,
+        req: RequestBuilder,
+    ) -> Result<T, SpotifyApiError> {
+        read_response(self.retry.send_non_idempotent(req).await?).await
+    }
+
+    pub async fn get_playlist_tracks_page(
+        &self,
+        playlist_id: &str,
+        offset: u32,
+    ) -> Result<Playlist, SpotifyApiError> {
+        let req = self.get(&format!(
+            "playlists/{playlist_id}/tracks?limit={PLAYLIST_TRACKS_LIMIT}&offset={offset}"
+        ));
+        self.send(req).await
     }
 
+    /// `next` を辿ってプレイリストの全ての楽曲を取得する
+    pub async fn get_playlist_tracks(
+        &self,
+        playlist_id: &str,
+    ) -> Result<Vec<PlaylistItem>, SpotifyApiError> {
+        let mut playlist = self.get_playlist_tracks_page(playlist_id, 0).await?;
+        let mut items = Vec::with_capacity(playlist.total as usize);
+        items.append(&mut playlist.items);
+        while let Some(next) = playlist.next {
+            playlist = self.send(self.get_url(&next)).await?;
+            items.append(&mut playlist.items);
+        }
+        Ok(items)
+    }
+
+    /// 100件ずつに分けてプレイリストに追加する
+    ///
+    /// `position` を指定するとその位置から順に挿入する。
+    /// 途中で失敗したときはそれ以降を送らずに、追加できた分を [`AddTracksError`] に含めて返す。
     pub async fn add_tracks_to_playlist(
         &self,
         playlist_id: &str,
         track_uris: Vec<String>,
-    ) -> Result<()> {
-        let body = serde_json::json!({
+        position: Option<u32>,
+    ) -> Result<Vec<AddedTracks>, AddTracksError> {
+        let mut added = Vec::<AddedTracks>::new();
+        let mut chunks = track_uris.chunks(ADD_TRACKS_LIMIT);
+        while let Some(chunk) = chunks.next() {
+            let position = position
+                .map(|position| position + added.iter().map(|a| a.uris.len() as u32).sum::<u32>());
+            match self.add_tracks_chunk(playlist_id, chunk, position).await {
+                Ok(SnapshotId { snapshot_id }) => added.push(AddedTracks {
+                    snapshot_id,
+                    uris: chunk.to_vec(),
+                }),
+                Err(source) => {
+                    let failed = chunk
+                        .iter()
+                        .chain(chunks.flatten())
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    return Err(AddTracksError {
+                        added,
+                        failed,
+                        source,
+                    });
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    async fn add_tracks_chunk(
+        &self,
+        playlist_id: &str,
+        track_uris: &[String],
+        position: Option<u32>,
+    ) -> Result<SnapshotId, SpotifyApiError> {
+        let mut body = serde_json::json!({
             "uris": track_uris,
         });
-        let _: serde_json::Value = self
+        if let Some(position) = position {
+            body["position"] = position.into();
+        }
+        let req = self
             .post(&format!("playlists/{playlist_id}/tracks"))
-            .body(body.to_string())
-            .send()
-            .await?
-            .json()
-            .await?;
-        Ok(())
+            .json(&body);
+        self.send_non_idempotent(req).await
     }
 
-    pub async fn get_current_users_profile(&self) -> Result<CurrentUsersProfile> {
-        let r = self.get("/me").send().await?.json().await?;
-        Ok(r)
+    pub async fn get_current_users_profile(&self) -> Result<CurrentUsersProfile, SpotifyApiError> {
+        self.send(self.get("me")).await
     }
 
-    pub async fn get_track(&self, track_id: &str) -> Result<Track> {
-        let r = self
-            .get(&format!("tracks/{track_id}"))
-            .send()
-            .await?
-            .json()
-            .await?;
-        Ok(r)
+    /// `next` を辿ってユーザーのプレイリストを全て取得する
+    pub async fn get_current_users_playlists(
+        &self,
+    ) -> Result<SimplifiedPlaylists, SpotifyApiError> {
+        let req = self.get(&format!("me/playlists?limit={PLAYLISTS_LIMIT}"));
+        let mut page: SimplifiedPlaylistsPage = self.send(req).await?;
+        let mut items = Vec::new();
+        items.append(&mut page.items);
+        while let Some(next) = page.next {
+            page = self.send(self.get_url(&next)).await?;
+            items.append(&mut page.items);
+        }
+        Ok(SimplifiedPlaylists { items })
+    }
+
+    pub async fn get_playlist(
+        &self,
+        playlist_id: &str,
+    ) -> Result<SimplifiedPlaylist, SpotifyApiError> {
+        let req = self.get(&format!(
+            "playlists/{playlist_id}?fields=id,name,owner(id),collaborative"
+        ));
+        self.send(req).await
+    }
+
+    pub async fn create_playlist(
+        &self,
+        user_id: &str,
+        name: &str,
+        description: &str,
+        public: bool,
+    ) -> Result<SimplifiedPlaylist, SpotifyApiError> {
+        let body = serde_json::json!({
+            "name": name,
+            "description": description,
+            "public": public,
+        });
+        let req = self.post(&format!("users/{user_id}/playlists")).json(&body);
+        self.send_non_idempotent(req).await
+    }
+
+    pub async fn get_track(&self, track_id: &str) -> Result<Track, SpotifyApiError> {
+        self.send(self.get(&format!("tracks/{track_id}"))).await
     }
 }
 
@@ -95,7 +300,7 @@ pub trait AddTracksToPlaylist {
         track_uris: Vec<&'b str>,
     ) -> Result<Vec<&'b str>>;
 
-    async fn get_playlist_tracks<'a>(&self, playlist_id: &'a str) -> Result<Playlist>;
+    async fn get_playlist_tracks<'a>(&self, playlist_id: &'a str) -> Result<Vec<PlaylistItem>>;
 }
 
 #[async_trait::async_trait]
@@ -105,26 +310,21 @@ impl AddTracksToPlaylist for SpotifyClient {
         playlist_id: &'a str,
         track_uris: Vec<&'b str>,
     ) -> Result<Vec<&'b str>> {
-        let body = serde_json::json!({
-            "uris": track_uris,
-        });
-        let _: serde_json::Value = self
-            .post(&format!("playlists/{playlist_id}/tracks"))
-            .body(body.to_string())
-            .send()
-            .await?
-            .json()
-            .await?;
+        let uris = track_uris.iter().map(|uri| uri.to_string()).collect();
+        SpotifyClient::add_tracks_to_playlist(self, playlist_id, uris, None).await?;
         Ok(track_uris)
     }
 
-    async fn get_playlist_tracks<'a>(&self, playlist_id: &'a str) -> Result<Playlist> {
-        let r = self
-            .get(&format!("playlists/{playlist_id}/tracks"))
-            .send()
-            .await?
-            .json()
-            .await?;
-        Ok(r)
+    async fn get_playlist_tracks<'a>(&self, playlist_id: &'a str) -> Result<Vec<PlaylistItem>> {
+        let items = SpotifyClient::get_playlist_tracks(self, playlist_id).await?;
+        Ok(items)
+    }
+}
+
+/// ステータスコードを見てからレスポンスを読む
+async fn read_response<T: DeserializeOwned>(res: Response) -> Result<T, SpotifyApiError> {
+    if !res.status().is_success() {
+        return Err(SpotifyApiError::from_response(res).await);
     }
+    Ok(res.json().await?)
 }
@@ -0,0 +1,161 @@
+use std::time::Duration;
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode};
+use serde::Deserialize;
+
+use crate::spotify::AddedTracks;
+
+/// Spotify Web APIが返したエラー
+#[derive(thiserror::Error, Debug)]
+pub enum SpotifyApiError {
+    /// アクセストークンが無効か期限切れ
+    #[error("unauthorized: {message}")]
+    Unauthorized { message: String },
+    /// スコープが足りないなど
+    #[error("forbidden: {message}")]
+    Forbidden { message: String },
+    #[error("not found: {message}")]
+    NotFound { message: String },
+    /// `Retry-After` 秒待ってからやり直す
+    #[error("rate limited: retry after {retry_after:?}")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("server error ({status}): {message}")]
+    Server { status: StatusCode, message: String },
+    #[error("unexpected status ({status}): {message}")]
+    Status { status: StatusCode, message: String },
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+/// `{ "error": { "status": 401, "message": "..." } }`
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    error: ErrorObject,
+}
+
+#[derive(Deserialize, Debug)]
+struct ErrorObject {
+    message: String,
+}
+
+impl SpotifyApiError {
+    /// 成功しなかったレスポンスからエラーを作る
+    pub async fn from_response(res: Response) -> SpotifyApiError {
+        let status = res.status();
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(Duration::from_secs);
+        let message = match res.json::<ErrorResponse>().await {
+            Ok(ErrorResponse { error }) => error.message,
+            Err(_) => status.canonical_reason().unwrap_or_default().to_string(),
+        };
+        match status {
+            StatusCode::UNAUTHORIZED => SpotifyApiError::Unauthorized { message },
+            StatusCode::FORBIDDEN => SpotifyApiError::Forbidden { message },
+            StatusCode::NOT_FOUND => SpotifyApiError::NotFound { message },
+            StatusCode::TOO_MANY_REQUESTS => SpotifyApiError::RateLimited { retry_after },
+            status if status.is_server_error() => SpotifyApiError::Server { status, message },
+            status => SpotifyApiError::Status { status, message },
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            SpotifyApiError::Unauthorized { .. } => Some(StatusCode::UNAUTHORIZED),
+            SpotifyApiError::Forbidden { .. } => Some(StatusCode::FORBIDDEN),
+            SpotifyApiError::NotFound { .. } => Some(StatusCode::NOT_FOUND),
+            SpotifyApiError::RateLimited { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            SpotifyApiError::Server { status, .. } | SpotifyApiError::Status { status, .. } => {
+                Some(*status)
+            }
+            SpotifyApiError::Request(e) => e.status(),
+        }
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(self, SpotifyApiError::Unauthorized { .. })
+    }
+}
+
+/// プレイリストへの追加が途中で失敗した
+#[derive(thiserror::Error, Debug)]
+#[error("failed to add {} tracks to the playlist: {source}", .failed.len())]
+pub struct AddTracksError {
+    /// 追加できた分
+    pub added: Vec<AddedTracks>,
+    /// 追加できなかった、または送らなかった楽曲
+    pub failed: Vec<String>,
+    #[source]
+    pub source: SpotifyApiError,
+}
+
+impl AddTracksError {
+    /// 追加できた楽曲
+    pub fn added_uris(&self) -> Vec<String> {
+        self.added
+            .iter()
+            .flat_map(|added| added.uris.iter().cloned())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, retry_after: Option<&str>, body: &str) -> Response {
+        let mut builder = http::Response::builder().status(status);
+        if let Some(retry_after) = retry_after {
+            builder = builder.header(RETRY_AFTER, retry_after);
+        }
+        Response::from(builder.body(body.to_string()).expect("response is valid"))
+    }
+
+    #[tokio::test]
+    async fn from_response() {
+        let body = r#"{ "error": { "status": 0, "message": "spotify says" } }"#;
+        let cases = [
+            (401, None, body, "unauthorized: spotify says"),
+            (403, None, body, "forbidden: spotify says"),
+            (404, None, body, "not found: spotify says"),
+            (429, Some("5"), body, "rate limited: retry after Some(5s)"),
+            (429, Some("soon"), body, "rate limited: retry after None"),
+            (429, None, "", "rate limited: retry after None"),
+            (
+                503,
+                None,
+                body,
+                "server error (503 Service Unavailable): spotify says",
+            ),
+            (
+                400,
+                None,
+                body,
+                "unexpected status (400 Bad Request): spotify says",
+            ),
+            // JSONでなければステータスの説明を使う
+            (
+                502,
+                None,
+                "<html>",
+                "server error (502 Bad Gateway): Bad Gateway",
+            ),
+        ];
+        for (status, retry_after, body, expected) in cases {
+            let e = SpotifyApiError::from_response(response(status, retry_after, body)).await;
+            assert_eq!(e.to_string(), expected);
+            assert_eq!(e.status().map(|status| status.as_u16()), Some(status));
+        }
+    }
+
+    #[tokio::test]
+    async fn is_unauthorized() {
+        let e = SpotifyApiError::from_response(response(401, None, "")).await;
+        assert!(e.is_unauthorized());
+        let e = SpotifyApiError::from_response(response(403, None, "")).await;
+        assert!(!e.is_unauthorized());
+    }
+}
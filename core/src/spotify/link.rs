@@ -0,0 +1,294 @@
+use std::fmt;
+
+use anyhow::Result;
+use reqwest::Url;
+
+/// 短縮URLのホスト
+const SHORT_LINK_HOSTS: [&str; 3] = ["spotify.link", "spoti.fi", "spotify.app.link"];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SpotifyLinkKind {
+    Track,
+    Album,
+    Playlist,
+    Artist,
+    Episode,
+}
+
+impl SpotifyLinkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpotifyLinkKind::Track => "track",
+            SpotifyLinkKind::Album => "album",
+            SpotifyLinkKind::Playlist => "playlist",
+            SpotifyLinkKind::Artist => "artist",
+            SpotifyLinkKind::Episode => "episode",
+        }
+    }
+
+    fn from_segment(segment: &str) -> Option<SpotifyLinkKind> {
+        match segment {
+            "track" => Some(SpotifyLinkKind::Track),
+            "album" => Some(SpotifyLinkKind::Album),
+            "playlist" => Some(SpotifyLinkKind::Playlist),
+            "artist" => Some(SpotifyLinkKind::Artist),
+            "episode" => Some(SpotifyLinkKind::Episode),
+            _ => None,
+        }
+    }
+}
+
+/// Spotifyのリンクが指しているもの
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct SpotifyLink {
+    pub kind: SpotifyLinkKind,
+    pub id: String,
+}
+
+impl fmt::Display for SpotifyLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "spotify:{}:{}", self.kind.as_str(), self.id)
+    }
+}
+
+impl SpotifyLink {
+    /// 以下のようなURLを解釈する (クエリ `?si=` などは無視する)
+    ///
+    /// - `spotify:track:{id}`
+    /// - `https://open.spotify.com/track/{id}`
+    /// - `https://open.spotify.com/intl-ja/track/{id}`
+    /// - `https://open.spotify.com/embed/track/{id}`
+    pub fn parse(url: &Url) -> Option<SpotifyLink> {
+        if url.scheme() == "spotify" {
+            let mut parts = url.path().split(':');
+            return match (parts.next(), parts.next(), parts.next()) {
+                (Some(kind), Some(id), None) => SpotifyLink::from_parts(kind, id),
+                _ => None,
+            };
+        }
+
+        if !matches!(url.scheme(), "http" | "https") {
+            return None;
+        }
+        if !matches!(
+            url.host_str(),
+            Some("open.spotify.com" | "play.spotify.com")
+        ) {
+            return None;
+        }
+        let mut segments = url
+            .path_segments()?
+            .filter(|segment| !segment.is_empty())
+            .skip_while(|segment| segment.starts_with("intl-") || *segment == "embed");
+        match (segments.next(), segments.next()) {
+            (Some(kind), Some(id)) => SpotifyLink::from_parts(kind, id),
+            _ => None,
+        }
+    }
+
+    fn from_parts(kind: &str, id: &str) -> Option<SpotifyLink> {
+        let kind = SpotifyLinkKind::from_segment(kind)?;
+        // IDはbase62の22文字
+        if id.len() != 22 || !id.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        Some(SpotifyLink {
+            kind,
+            id: id.to_string(),
+        })
+    }
+
+    /// `spotify:track:{id}` の形式にする
+    pub fn uri(&self) -> String {
+        self.to_string()
+    }
+
+    pub fn is_track(&self) -> bool {
+        self.kind == SpotifyLinkKind::Track
+    }
+
+    /// `spotify.link` や `spoti.fi` の短縮URLか
+    pub fn is_short_link(url: &Url) -> bool {
+        url.host_str()
+            .map(|host| SHORT_LINK_HOSTS.contains(&host))
+            .unwrap_or(false)
+    }
+}
+
+/// 短縮URLを展開する
+#[async_trait::async_trait]
+pub trait ResolveShortLink {
+    async fn resolve(&self, url: &Url) -> Result<Url>;
+}
+
+/// リダイレクトを辿って短縮URLを展開する
+#[derive(Clone, Debug, Default)]
+pub struct HttpShortLinkResolver {
+    client: reqwest::Client,
+}
+
+impl HttpShortLinkResolver {
+    pub fn new(client: reqwest::Client) -> HttpShortLinkResolver {
+        HttpShortLinkResolver { client }
+    }
+}
+
+#[async_trait::async_trait]
+impl ResolveShortLink for HttpShortLinkResolver {
+    async fn resolve(&self, url: &Url) -> Result<Url> {
+        let res = self.client.get(url.clone()).send().await?;
+        Ok(res.url().to_owned())
+    }
+}
+
+/// URLからSpotifyのリンクを取り出す
+#[derive(Clone, Debug)]
+pub struct SpotifyLinkExtractor<R> {
+    resolver: R,
+}
+
+impl<R: ResolveShortLink + Sync> SpotifyLinkExtractor<R> {
+    pub fn new(resolver: R) -> SpotifyLinkExtractor<R> {
+        SpotifyLinkExtractor { resolver }
+    }
+
+    /// 短縮URLであれば展開してから解釈する
+    pub async fn extract(&self, url: &Url) -> Result<Option<SpotifyLink>> {
+        if SpotifyLink::is_short_link(url) {
+            let url = self.resolver.resolve(url).await?;
+            return Ok(SpotifyLink::parse(&url));
+        }
+        Ok(SpotifyLink::parse(url))
+    }
+
+    /// 楽曲のリンクであれば `spotify:track:{id}` を返す
+    pub async fn extract_track_uri(&self, url: &Url) -> Result<Option<String>> {
+        let link = self.extract(url).await?;
+        Ok(link.filter(SpotifyLink::is_track).map(|link| link.uri()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use anyhow::{anyhow, Result};
+    use reqwest::Url;
+
+    use super::{ResolveShortLink, SpotifyLink, SpotifyLinkExtractor, SpotifyLinkKind};
+
+    const TRACK_ID: &str = "4uLU6hMCjMI75M1A2tKUQC";
+
+    /// 決まったURLに展開する
+    struct StubResolver(HashMap<String, String>);
+
+    #[async_trait::async_trait]
+    impl ResolveShortLink for StubResolver {
+        async fn resolve(&self, url: &Url) -> Result<Url> {
+            let resolved = self
+                .0
+                .get(url.as_str())
+                .ok_or_else(|| anyhow!("unknown short link {url}"))?;
+            Ok(Url::parse(resolved)?)
+        }
+    }
+
+    fn parse(url: &str) -> Option<SpotifyLink> {
+        SpotifyLink::parse(&Url::parse(url).unwrap())
+    }
+
+    fn track(id: &str) -> Option<SpotifyLink> {
+        Some(SpotifyLink {
+            kind: SpotifyLinkKind::Track,
+            id: id.to_string(),
+        })
+    }
+
+    #[test]
+    fn parse_track_urls() {
+        let urls = [
+            format!("https://open.spotify.com/track/{TRACK_ID}"),
+            format!("https://open.spotify.com/track/{TRACK_ID}?si=0123456789abcdef"),
+            format!("https://open.spotify.com/intl-ja/track/{TRACK_ID}"),
+            format!("https://open.spotify.com/intl-pt-BR/track/{TRACK_ID}?si=abc"),
+            format!("https://open.spotify.com/embed/track/{TRACK_ID}"),
+            format!("https://play.spotify.com/track/{TRACK_ID}"),
+            format!("spotify:track:{TRACK_ID}"),
+        ];
+        for url in urls {
+            assert_eq!(parse(&url), track(TRACK_ID), "{url}");
+        }
+    }
+
+    #[test]
+    fn parse_other_kinds() {
+        let album = parse(&format!("https://open.spotify.com/album/{TRACK_ID}")).unwrap();
+        assert_eq!(album.kind, SpotifyLinkKind::Album);
+        assert!(!album.is_track());
+        let episode = parse(&format!("spotify:episode:{TRACK_ID}")).unwrap();
+        assert_eq!(episode.kind, SpotifyLinkKind::Episode);
+        assert_eq!(episode.uri(), format!("spotify:episode:{TRACK_ID}"));
+        assert_eq!(
+            parse(&format!("https://open.spotify.com/user/{TRACK_ID}")),
+            None
+        );
+    }
+
+    #[test]
+    fn reject_invalid_urls() {
+        let urls = [
+            // IDは22文字
+            "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQ".to_string(),
+            "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQCx".to_string(),
+            "https://open.spotify.com/track/4uLU6hMCjMI75M1A2tKUQ-".to_string(),
+            format!("spotify:track:{TRACK_ID}:extra"),
+            format!("https://example.com/track/{TRACK_ID}"),
+            format!("ftp://open.spotify.com/track/{TRACK_ID}"),
+            "https://open.spotify.com/track".to_string(),
+        ];
+        for url in urls {
+            assert_eq!(parse(&url), None, "{url}");
+        }
+    }
+
+    #[test]
+    fn detect_short_links() {
+        for url in ["https://spotify.link/abc", "https://spoti.fi/abc"] {
+            assert!(
+                SpotifyLink::is_short_link(&Url::parse(url).unwrap()),
+                "{url}"
+            );
+        }
+        let url = Url::parse(&format!("https://open.spotify.com/track/{TRACK_ID}")).unwrap();
+        assert!(!SpotifyLink::is_short_link(&url));
+    }
+
+    #[tokio::test]
+    async fn extract_track_uri_from_short_link() -> Result<()> {
+        let resolver = StubResolver(HashMap::from([
+            (
+                "https://spotify.link/track".to_string(),
+                format!("https://open.spotify.com/track/{TRACK_ID}?si=abc"),
+            ),
+            (
+                "https://spotify.link/album".to_string(),
+                format!("https://open.spotify.com/album/{TRACK_ID}"),
+            ),
+        ]));
+        let extractor = SpotifyLinkExtractor::new(resolver);
+        let uri = extractor
+            .extract_track_uri(&Url::parse("https://spotify.link/track")?)
+            .await?;
+        assert_eq!(uri, Some(format!("spotify:track:{TRACK_ID}")));
+        let uri = extractor
+            .extract_track_uri(&Url::parse("https://spotify.link/album")?)
+            .await?;
+        assert_eq!(uri, None);
+        // 展開できなければエラーにする
+        let result = extractor
+            .extract_track_uri(&Url::parse("https://spotify.link/unknown")?)
+            .await;
+        assert!(result.is_err());
+        Ok(())
+    }
+}
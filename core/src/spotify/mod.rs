@@ -1,9 +1,17 @@
 mod auth;
 mod client;
+mod error;
+mod link;
 
 pub use self::{
     auth::SpotifyOAuth2Client,
     client::{
-        AddTracksToPlaylist, CurrentUsersProfile, Playlist, PlaylistItem, SpotifyClient, Track,
+        AddTracksToPlaylist, AddedTracks, CurrentUsersProfile, Episode, Image, Playlist,
+        PlaylistItem, PlaylistOwner, PlaylistTrack, SimplifiedPlaylist, SimplifiedPlaylists,
+        SpotifyClient, Track, SPOTIFY_API_BASE_URL,
+    },
+    error::{AddTracksError, SpotifyApiError},
+    link::{
+        HttpShortLinkResolver, ResolveShortLink, SpotifyLink, SpotifyLinkExtractor, SpotifyLinkKind,
     },
 };
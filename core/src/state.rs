@@ -1,17 +1,28 @@
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-};
+use std::sync::Arc;
 
-use anyhow::{bail, Result};
+use anyhow::Result;
 use sea_orm::DatabaseConnection;
 use serde::Deserialize;
 
+use crate::{
+    spotify::{SpotifyClient, SPOTIFY_API_BASE_URL},
+    twitter::{TwitterClient, TWITTER_API_BASE_URL},
+    HttpConfig, OAuth2Provider, OAuth2VerifierConfig, OAuth2Verifiers, RetryPolicy,
+    SpotifyOAuth2Client, TwitterOAuth2Client,
+};
+
 #[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
 pub struct OAuth2ClientCredential {
     pub client_id: String,
     pub client_secret: String,
     pub redirect_uri: String,
+    /// 以下は省略すると本物のURLを使う。モックサーバーに向けるときに指定する
+    pub auth_url: Option<String>,
+    pub token_url: Option<String>,
+    /// トークンを失効させるURL。Twitterのみ使う
+    pub revocation_url: Option<String>,
+    /// `https://api.spotify.com/v1` や `https://api.twitter.com/2` にあたるURL
+    pub api_base_url: Option<String>,
 }
 
 #[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
@@ -20,51 +31,83 @@ pub struct OAuth2ClientCredentials {
     pub spotify: OAuth2ClientCredential,
 }
 
-#[derive(Clone, Debug)]
-pub struct OAuth2Verifiers(Arc<Mutex<HashMap<String, String>>>);
-
-impl OAuth2Verifiers {
-    pub fn new() -> OAuth2Verifiers {
-        OAuth2Verifiers(Arc::new(Mutex::new(HashMap::new())))
-    }
-
-    pub fn remove(&self, state: &str) -> Result<String> {
-        let Ok(mut verifiers) = self.0.lock() else {
-            bail!("Lock failed");
-        };
-        let Some(verifier) = verifiers.remove(state) else {
-            bail!("Verifier is none");
-        };
-        Ok(verifier)
-    }
-
-    pub fn insert(&self, state: String, verifier: String) -> Result<()> {
-        let Ok(mut verifiers) = self.0.lock() else {
-            bail!("Lock failed");
-        };
-        verifiers.insert(state, verifier);
-        Ok(())
-    }
-}
-
 #[derive(Clone, Debug)]
 pub struct AppState {
     pub connection: DatabaseConnection,
     pub spotify_verifiers: OAuth2Verifiers,
     pub twitter_verifiers: OAuth2Verifiers,
     pub oauth2_client_credentials: Arc<OAuth2ClientCredentials>,
+    pub retry_policy: RetryPolicy,
+    /// API呼び出しで共有するクライアント
+    pub http_client: reqwest::Client,
+    /// トークンエンドポイント用のリダイレクトを辿らないクライアント
+    pub oauth2_http_client: reqwest::Client,
 }
 
 impl AppState {
     pub fn new(
         connection: DatabaseConnection,
         oauth2_client_credentials: OAuth2ClientCredentials,
-    ) -> AppState {
-        AppState {
+        retry_policy: RetryPolicy,
+        http_config: &HttpConfig,
+        verifier_config: &OAuth2VerifierConfig,
+    ) -> Result<AppState> {
+        let spotify_verifiers =
+            OAuth2Verifiers::new(connection.clone(), OAuth2Provider::Spotify, verifier_config);
+        let twitter_verifiers =
+            OAuth2Verifiers::new(connection.clone(), OAuth2Provider::Twitter, verifier_config);
+        Ok(AppState {
             connection,
-            spotify_verifiers: OAuth2Verifiers::new(),
-            twitter_verifiers: OAuth2Verifiers::new(),
+            spotify_verifiers,
+            twitter_verifiers,
             oauth2_client_credentials: Arc::new(oauth2_client_credentials),
-        }
+            retry_policy,
+            http_client: http_config.build(true)?,
+            oauth2_http_client: http_config.build(false)?,
+        })
+    }
+
+    pub fn spotify_oauth2_client(&self) -> Result<SpotifyOAuth2Client> {
+        SpotifyOAuth2Client::new(
+            &self.oauth2_client_credentials.spotify,
+            self.oauth2_http_client.clone(),
+        )
+    }
+
+    pub fn twitter_oauth2_client(&self) -> Result<TwitterOAuth2Client> {
+        TwitterOAuth2Client::new(
+            &self.oauth2_client_credentials.twitter,
+            self.oauth2_http_client.clone(),
+        )
+    }
+
+    pub fn spotify_client(&self, access_token: String) -> SpotifyClient {
+        let base_url = self
+            .oauth2_client_credentials
+            .spotify
+            .api_base_url
+            .as_deref()
+            .unwrap_or(SPOTIFY_API_BASE_URL);
+        SpotifyClient::new(
+            access_token,
+            base_url.trim_end_matches('/').to_string(),
+            self.retry_policy.clone(),
+            self.http_client.clone(),
+        )
+    }
+
+    pub fn twitter_client(&self, access_token: String) -> TwitterClient {
+        let base_url = self
+            .oauth2_client_credentials
+            .twitter
+            .api_base_url
+            .as_deref()
+            .unwrap_or(TWITTER_API_BASE_URL);
+        TwitterClient::new(
+            access_token,
+            base_url.trim_end_matches('/').to_string(),
+            self.retry_policy.clone(),
+            self.http_client.clone(),
+        )
     }
 }
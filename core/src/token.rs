@@ -0,0 +1,24 @@
+use chrono::{DateTime, Duration, Utc};
+use oauth2::{basic::BasicTokenResponse, TokenResponse};
+
+/// トークンエンドポイントから受け取ったトークン
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct OAuth2Token {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl From<BasicTokenResponse> for OAuth2Token {
+    fn from(token: BasicTokenResponse) -> OAuth2Token {
+        let expires_at = token
+            .expires_in()
+            .and_then(|expires_in| Duration::from_std(expires_in).ok())
+            .map(|expires_in| Utc::now() + expires_in);
+        OAuth2Token {
+            access_token: token.access_token().secret().to_owned(),
+            refresh_token: token.refresh_token().map(|s| s.secret().to_owned()),
+            expires_at,
+        }
+    }
+}
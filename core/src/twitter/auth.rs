@@ -2,12 +2,15 @@ use std::ops::Deref;
 
 use anyhow::Result;
 use oauth2::{
-    basic::BasicClient, reqwest::async_http_client, url::Url, AuthorizationCode, PkceCodeVerifier,
-    TokenResponse,
+    basic::BasicClient, url::Url, AuthorizationCode, PkceCodeVerifier, RefreshToken,
+    StandardRevocableToken,
 };
 
+use crate::{http::oauth2_http_client, OAuth2ClientCredential, OAuth2Token};
+
 const TWITTER_AUTH_URL: &str = "https://twitter.com/i/oauth2/authorize";
 const TWITTER_TOKEN_URL: &str = "https://api.twitter.com/2/oauth2/token";
+const TWITTER_REVOCATION_URL: &str = "https://api.twitter.com/2/oauth2/revoke";
 /// https://developer.twitter.com/en/docs/authentication/oauth-2-0/authorization-code
 /// https://developer.twitter.com/en/docs/api-reference-index
 const TWITTER_SCOPES: [&str; 7] = [
@@ -23,6 +26,7 @@ const TWITTER_SCOPES: [&str; 7] = [
 #[derive(Debug)]
 pub struct TwitterOAuth2Client {
     inner: BasicClient,
+    http_client: reqwest::Client,
 }
 
 impl Deref for TwitterOAuth2Client {
@@ -33,20 +37,31 @@ impl Deref for TwitterOAuth2Client {
 }
 
 impl TwitterOAuth2Client {
+    /// `auth_url` と `token_url` が指定されていなければ本物のURLを使う
     pub fn new(
-        client_id: String,
-        client_secret: String,
-        redirect_url: String,
+        credential: &OAuth2ClientCredential,
+        http_client: reqwest::Client,
     ) -> Result<TwitterOAuth2Client> {
         use oauth2::*;
-        let client_id = ClientId::new(client_id.to_string());
-        let client_secret = ClientSecret::new(client_secret.to_string());
-        let auth_url = AuthUrl::new(TWITTER_AUTH_URL.to_string())?;
-        let token_url = TokenUrl::new(TWITTER_TOKEN_URL.to_string())?;
-        let redirect_url = RedirectUrl::new(redirect_url)?;
+        let client_id = ClientId::new(credential.client_id.clone());
+        let client_secret = ClientSecret::new(credential.client_secret.clone());
+        let auth_url = credential.auth_url.as_deref().unwrap_or(TWITTER_AUTH_URL);
+        let auth_url = AuthUrl::new(auth_url.to_string())?;
+        let token_url = credential.token_url.as_deref().unwrap_or(TWITTER_TOKEN_URL);
+        let token_url = TokenUrl::new(token_url.to_string())?;
+        let revocation_url = credential
+            .revocation_url
+            .as_deref()
+            .unwrap_or(TWITTER_REVOCATION_URL);
+        let revocation_url = RevocationUrl::new(revocation_url.to_string())?;
+        let redirect_url = RedirectUrl::new(credential.redirect_uri.clone())?;
         let client = BasicClient::new(client_id, Some(client_secret), auth_url, Some(token_url))
-            .set_redirect_uri(redirect_url);
-        Ok(TwitterOAuth2Client { inner: client })
+            .set_redirect_uri(redirect_url)
+            .set_revocation_uri(revocation_url);
+        Ok(TwitterOAuth2Client {
+            inner: client,
+            http_client,
+        })
     }
 
     pub fn create_authorize_urls(&self) -> (Url, String, String) {
@@ -68,19 +83,32 @@ impl TwitterOAuth2Client {
         )
     }
 
-    pub async fn exchange_code(
-        &self,
-        verifier: String,
-        code: String,
-    ) -> Result<(String, Option<String>)> {
+    pub async fn exchange_code(&self, verifier: String, code: String) -> Result<OAuth2Token> {
         let token = self
             .inner
             .exchange_code(AuthorizationCode::new(code))
             .set_pkce_verifier(PkceCodeVerifier::new(verifier))
-            .request_async(async_http_client)
+            .request_async(|request| oauth2_http_client(self.http_client.clone(), request))
+            .await?;
+        Ok(token.into())
+    }
+
+    pub async fn exchange_refresh_token(&self, refresh_token: String) -> Result<OAuth2Token> {
+        let token = self
+            .inner
+            .exchange_refresh_token(&RefreshToken::new(refresh_token))
+            .request_async(|request| oauth2_http_client(self.http_client.clone(), request))
+            .await?;
+        Ok(token.into())
+    }
+
+    /// リフレッシュトークンを失効させる。アクセストークンも一緒に使えなくなる
+    pub async fn revoke_refresh_token(&self, refresh_token: String) -> Result<()> {
+        let token = StandardRevocableToken::RefreshToken(RefreshToken::new(refresh_token));
+        self.inner
+            .revoke_token(token)?
+            .request_async(|request| oauth2_http_client(self.http_client.clone(), request))
             .await?;
-        let access_token = token.access_token().secret().to_owned();
-        let refresh_token = token.refresh_token().map(|s| s.secret().to_owned());
-        Ok((access_token, refresh_token))
+        Ok(())
     }
 }
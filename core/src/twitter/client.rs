@@ -1,129 +1,386 @@
-use anyhow::{bail, Result};
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
 use derive_new::new;
-use reqwest::Url;
+use reqwest::{RequestBuilder, Url};
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Serialize,
+};
 use twitter_v2::{
-    authorization::BearerToken,
-    data::{FullTextEntities, UrlEntity},
+    data::{
+        Attachments, Expansions, FullTextEntities, HashtagEntity, Media, ReferencedTweet,
+        ReferencedTweetKind, UrlEntity,
+    },
     id::NumericId,
-    meta::TweetsMeta,
-    query::{TweetExpansion, TweetField, UserField},
-    TwitterApi, TwitterApiWithUserCtx, User,
+    User,
+};
+
+use crate::{
+    twitter::{TweetSource, TwitterApiError},
+    RetryPolicy,
 };
 
+pub const TWITTER_API_BASE_URL: &str = "https://api.twitter.com/2";
+
+/// `{ "data": ..., "includes": ..., "meta": ... }`
+#[derive(Deserialize, Debug)]
+pub struct Payload<T, M = IgnoredAny> {
+    pub data: Option<T>,
+    pub includes: Option<Expansions>,
+    pub meta: Option<M>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+pub struct TimelineMeta {
+    pub newest_id: Option<String>,
+    pub oldest_id: Option<String>,
+    pub next_token: Option<String>,
+    pub result_count: Option<u64>,
+}
+
+#[derive(new, Clone, Debug)]
+pub struct TwitterClient {
+    token: String,
+    /// `https://api.twitter.com/2` など
+    base_url: String,
+    retry: RetryPolicy,
+    client: reqwest::Client,
+}
+
+impl TwitterClient {
+    fn get(&self, path: &str) -> RequestBuilder {
+        self.client
+            .get(format!("{}/{path}", self.base_url))
+            .bearer_auth(&self.token)
+    }
+
+    /// ステータスコードを見てからレスポンスを読む
+    async fn send<T: DeserializeOwned>(&self, req: RequestBuilder) -> Result<T, TwitterApiError> {
+        let res = self.retry.send(req).await?;
+        if !res.status().is_success() {
+            return Err(TwitterApiError::from_response(res).await);
+        }
+        Ok(res.json().await?)
+    }
+
+    pub async fn get_users_me(&self) -> Result<User, TwitterApiError> {
+        let req = self
+            .get("users/me")
+            .query(&[("user.fields", "username,name,profile_image_url")]);
+        let payload: Payload<User> = self.send(req).await?;
+        payload.data.ok_or(TwitterApiError::MissingData)
+    }
+
+    /// ツイートの一覧を返すエンドポイントを呼ぶ
+    pub async fn get_tweets(
+        &self,
+        path: &str,
+        query: &[(&str, &str)],
+    ) -> Result<Payload<Vec<twitter_v2::Tweet>, TimelineMeta>, TwitterApiError> {
+        self.send(self.get(path).query(query)).await
+    }
+}
+
+/// 前回読んだところまで一度にさかのぼるページ数の上限
+///
+/// 逆時系列のタイムラインはそもそも最新の800件までしか取得できない。
+const MAX_PAGES: usize = 8;
+
+/// 前回読んだところまで [`MAX_PAGES`] ページでさかのぼれず、その間のツイートを読み飛ばした
+#[derive(thiserror::Error, Debug)]
+#[error("{path} has more than {MAX_PAGES} new pages, older tweets are skipped")]
+pub struct TimelineTruncated {
+    pub path: String,
+}
+
 pub struct TimelineReader {
-    client: TwitterApiWithUserCtx<BearerToken>,
-    newest_id: Option<String>,
-    next_token: Option<String>,
+    client: TwitterClient,
+    user_id: u64,
+    source: TweetSource,
+    /// ここまでは読んだというツイートのID
+    since_id: Option<String>,
 }
 
-#[derive(new, Debug)]
+#[derive(Debug)]
 pub struct Tweet {
     pub id: u64,
     pub text: String,
     pub urls: Vec<Url>,
     pub username: String,
     pub author_id: u64,
+    /// 著者の表示名
+    pub author_name: String,
+    pub created_at: Option<DateTime<Utc>>,
+    /// `#` を除いたハッシュタグ
+    pub hashtags: Vec<String>,
+    /// リプライ先のユーザー
+    pub in_reply_to_user_id: Option<u64>,
+    /// リツイート、引用、リプライしたツイート
+    pub referenced_tweets: Vec<TweetReference>,
+    /// 引用したツイートに含まれるURL
+    pub quoted_urls: Vec<Url>,
+    /// 添付された画像や動画のURL
+    pub media_urls: Vec<Url>,
+}
+
+impl Tweet {
+    pub fn is_retweet(&self) -> bool {
+        self.referenced_tweets
+            .iter()
+            .any(|reference| reference.kind == TweetReferenceKind::Retweeted)
+    }
+
+    /// 本文と引用したツイートに含まれるURL
+    pub fn all_urls(&self) -> impl Iterator<Item = &Url> {
+        self.urls.iter().chain(&self.quoted_urls)
+    }
+}
+
+#[derive(Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum TweetReferenceKind {
+    Retweeted,
+    Quoted,
+    RepliedTo,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct TweetReference {
+    pub kind: TweetReferenceKind,
+    pub id: u64,
 }
 
 impl TimelineReader {
-    pub async fn new(access_token: String) -> Result<TimelineReader> {
-        let auth = BearerToken::new(access_token);
-        let client = TwitterApi::new(auth);
-        let client = client.with_user_ctx().await?;
+    pub async fn new(client: TwitterClient, source: TweetSource) -> Result<TimelineReader> {
+        let me = client.get_users_me().await?;
         Ok(TimelineReader {
             client,
-            newest_id: None,
-            next_token: None,
+            user_id: me.id.as_u64(),
+            source,
+            since_id: None,
         })
     }
 
     pub fn me(&self) -> u64 {
-        self.client.user_id().as_u64()
+        self.user_id
+    }
+
+    pub fn source(&self) -> &TweetSource {
+        &self.source
+    }
+
+    pub fn since_id(&self) -> Option<&str> {
+        self.since_id.as_deref()
+    }
+
+    /// 前回読んだところを設定する
+    pub fn set_since_id(&mut self, since_id: Option<String>) {
+        self.since_id = since_id;
     }
 
-    pub async fn next(&mut self) -> Result<Vec<Tweet>> {
-        let req = {
-            let mut req = self.client.get_my_reverse_chronological_timelines();
-            req.user_fields([UserField::CreatedAt, UserField::Username, UserField::Name])
-                .tweet_fields([
-                    TweetField::CreatedAt,
-                    TweetField::Attachments,
-                    TweetField::Entities,
-                ])
-                .expansions([TweetExpansion::AuthorId]);
-            if let Some(t) = &self.next_token {
-                req.pagination_token(t);
+    fn query() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("user.fields", "created_at,username,name"),
+            (
+                "tweet.fields",
+                "created_at,attachments,entities,in_reply_to_user_id,referenced_tweets",
+            ),
+            ("media.fields", "url,preview_image_url"),
+            (
+                "expansions",
+                "author_id,referenced_tweets.id,attachments.media_keys",
+            ),
+            ("max_results", "100"),
+        ]
+    }
+
+    /// `since_id` より新しいツイートをページを遡って全て読み、`since_id` を進める
+    ///
+    /// 新しいツイートがなければ空を返す。`since_id` がない初回は最新の1ページだけ読む。
+    /// `since_id` を指定できないものは、前回の先頭のツイートに辿り着くまで読む。
+    /// [`MAX_PAGES`] ページで辿り着けなければ、読んだ分と [`TimelineTruncated`] を返す。
+    pub async fn poll(&mut self) -> Result<(Vec<Tweet>, Option<TimelineTruncated>)> {
+        let path = self.source.path(self.user_id);
+        let supports_since_id = self.source.supports_since_id();
+        let mut tweets = Vec::new();
+        let mut newest_id = None;
+        let mut pagination_token: Option<String> = None;
+        let mut truncated = None;
+        for page in 1..=MAX_PAGES {
+            let mut query = TimelineReader::query();
+            if let (Some(since_id), true) = (&self.since_id, supports_since_id) {
+                query.push(("since_id", since_id.as_str()));
+            }
+            if let Some(token) = &pagination_token {
+                query.push(("pagination_token", token.as_str()));
+            }
+            let res = self.client.get_tweets(&path, &query).await?;
+            let ids = res
+                .data
+                .iter()
+                .flatten()
+                .map(|tweet| tweet.id.as_u64().to_string())
+                .collect::<Vec<_>>();
+            // 1ページ目の先頭が一番新しい
+            if newest_id.is_none() {
+                newest_id = ids.first().cloned();
+            }
+            let mut page_tweets = to_tweets(&res);
+            let reached = match &self.since_id {
+                Some(since_id) if !supports_since_id => {
+                    match ids.iter().position(|id| id == since_id) {
+                        Some(position) => {
+                            let unread = &ids[..position];
+                            page_tweets.retain(|tweet| unread.contains(&tweet.id.to_string()));
+                            true
+                        }
+                        None => false,
+                    }
+                }
+                _ => false,
+            };
+            tweets.append(&mut page_tweets);
+            pagination_token = res.meta.and_then(|meta| meta.next_token);
+            if reached || self.since_id.is_none() || pagination_token.is_none() {
+                break;
+            }
+            if page == MAX_PAGES {
+                truncated = Some(TimelineTruncated { path: path.clone() });
             }
-            req
-        };
-        let res = req.send().await?;
-
-        if let Some(TweetsMeta {
-            next_token,
-            newest_id,
-            ..
-        }) = res.meta()
-        {
-            self.next_token = next_token.to_owned();
-            self.newest_id = newest_id.to_owned().into();
         }
+        if newest_id.is_some() {
+            self.since_id = newest_id;
+        }
+        Ok((tweets, truncated))
+    }
+}
 
-        let users = res.includes().and_then(|includes| includes.users.as_ref());
-        let get_user = move |user_id: NumericId| -> Option<&User> {
-            users.and_then(|users| users.iter().find(|user| user.id == user_id))
-        };
-        let tweets = match res.data() {
-            Some(tweets) => tweets,
-            None => bail!("no tweets"),
-        };
-        let tweets = tweets
+/// 著者が分かるツイートだけを取り出す
+fn to_tweets(res: &Payload<Vec<twitter_v2::Tweet>, TimelineMeta>) -> Vec<Tweet> {
+    let includes = res.includes.as_ref();
+    let users = includes.and_then(|includes| includes.users.as_ref());
+    let get_user = move |user_id: NumericId| -> Option<&User> {
+        users.and_then(|users| users.iter().find(|user| user.id == user_id))
+    };
+    // 引用したツイートは `includes.tweets` に入っている
+    let included_tweets = includes.and_then(|includes| includes.tweets.as_ref());
+    let get_tweet = move |tweet_id: NumericId| -> Option<&twitter_v2::Tweet> {
+        included_tweets.and_then(|tweets| tweets.iter().find(|tweet| tweet.id == tweet_id))
+    };
+    let media = includes.and_then(|includes| includes.media.as_ref());
+    let Some(tweets) = &res.data else {
+        return Vec::new();
+    };
+    tweets
+        .iter()
+        .flat_map(
+            |twitter_v2::Tweet {
+                 text,
+                 author_id,
+                 entities,
+                 id: tweet_id,
+                 created_at,
+                 in_reply_to_user_id,
+                 referenced_tweets,
+                 attachments,
+                 ..
+             }| {
+                let quoted_urls = referenced_tweets
+                    .iter()
+                    .flatten()
+                    .filter(|referenced| matches!(referenced.kind, ReferencedTweetKind::Quoted))
+                    .flat_map(|referenced| get_tweet(referenced.id))
+                    .flat_map(|quoted| to_expanded_urls(&quoted.entities))
+                    .collect();
+                let referenced_tweets = referenced_tweets
+                    .iter()
+                    .flatten()
+                    .map(|ReferencedTweet { kind, id }| TweetReference {
+                        kind: match kind {
+                            ReferencedTweetKind::Retweeted => TweetReferenceKind::Retweeted,
+                            ReferencedTweetKind::Quoted => TweetReferenceKind::Quoted,
+                            ReferencedTweetKind::RepliedTo => TweetReferenceKind::RepliedTo,
+                        },
+                        id: id.as_u64(),
+                    })
+                    .collect::<Vec<_>>();
+                let media_urls = match attachments {
+                    Some(Attachments {
+                        media_keys: Some(keys),
+                        ..
+                    }) => media
+                        .iter()
+                        .flatten()
+                        .filter(|media| keys.contains(&media.media_key))
+                        .flat_map(
+                            |Media {
+                                 url,
+                                 preview_image_url,
+                                 ..
+                             }| {
+                                url.as_ref().or(preview_image_url.as_ref()).cloned()
+                            },
+                        )
+                        .collect(),
+                    _ => Default::default(),
+                };
+                let hashtags = match &entities {
+                    Some(FullTextEntities {
+                        hashtags: Some(hashtags),
+                        ..
+                    }) => hashtags
+                        .iter()
+                        .map(|HashtagEntity { tag, .. }| tag.to_owned())
+                        .collect(),
+                    _ => Default::default(),
+                };
+                let created_at = created_at.and_then(|created_at| {
+                    Utc.timestamp_opt(created_at.unix_timestamp(), created_at.nanosecond())
+                        .single()
+                });
+                author_id.and_then(get_user).map(
+                    |twitter_v2::User {
+                         username, name, id, ..
+                     }| Tweet {
+                        id: tweet_id.as_u64(),
+                        text: text.to_owned(),
+                        urls: to_expanded_urls(entities),
+                        username: username.to_owned(),
+                        author_id: id.as_u64(),
+                        author_name: name.to_owned(),
+                        created_at,
+                        hashtags,
+                        in_reply_to_user_id: in_reply_to_user_id.map(|id| id.as_u64()),
+                        referenced_tweets,
+                        quoted_urls,
+                        media_urls,
+                    },
+                )
+            },
+        )
+        .collect::<Vec<_>>()
+}
+
+fn to_expanded_urls(entities: &Option<FullTextEntities>) -> Vec<Url> {
+    match entities {
+        Some(FullTextEntities {
+            urls: Some(urls), ..
+        }) => urls
             .iter()
-            .flat_map(
-                |twitter_v2::Tweet {
-                     text,
-                     author_id,
-                     entities,
-                     id: tweet_id,
-                     ..
-                 }| {
-                    fn to_expanded_urls(urls: &[UrlEntity]) -> Vec<Url> {
-                        urls.iter()
-                            .flat_map(|UrlEntity { expanded_url, .. }| Url::parse(expanded_url))
-                            .collect::<Vec<_>>()
-                    }
-                    let urls = match &entities {
-                        Some(FullTextEntities {
-                            urls: Some(urls), ..
-                        }) => to_expanded_urls(urls),
-                        _ => Default::default(),
-                    };
-                    author_id
-                        .and_then(get_user)
-                        .map(|twitter_v2::User { username, id, .. }| {
-                            Tweet::new(
-                                tweet_id.as_u64(),
-                                text.to_owned(),
-                                urls,
-                                username.to_owned(),
-                                id.as_u64(),
-                            )
-                        })
-                },
-            )
-            .collect::<Vec<_>>();
-        Ok(tweets)
+            .flat_map(|UrlEntity { expanded_url, .. }| Url::parse(expanded_url))
+            .collect(),
+        _ => Default::default(),
     }
 }
 
 #[async_trait::async_trait]
 pub trait GetTimeline {
-    async fn get_timeline(&mut self) -> Result<Vec<Tweet>>;
+    async fn get_timeline(&mut self) -> Result<(Vec<Tweet>, Option<TimelineTruncated>)>;
 }
 
 #[async_trait::async_trait]
 impl GetTimeline for TimelineReader {
-    async fn get_timeline(&mut self) -> Result<Vec<Tweet>> {
-        let tweets = self.next().await?;
-        Ok(tweets)
+    async fn get_timeline(&mut self) -> Result<(Vec<Tweet>, Option<TimelineTruncated>)> {
+        self.poll().await
     }
 }
@@ -0,0 +1,43 @@
+use reqwest::{Response, StatusCode};
+use serde::Deserialize;
+
+/// Twitter APIが返したエラー
+#[derive(thiserror::Error, Debug)]
+pub enum TwitterApiError {
+    #[error("twitter api error ({status}): {detail}")]
+    Status { status: StatusCode, detail: String },
+    #[error("response does not include data")]
+    MissingData,
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+}
+
+/// `{ "title": "Unauthorized", "status": 401, "detail": "..." }`
+#[derive(Deserialize, Debug)]
+struct ErrorResponse {
+    detail: String,
+}
+
+impl TwitterApiError {
+    /// 成功しなかったレスポンスからエラーを作る
+    pub async fn from_response(res: Response) -> TwitterApiError {
+        let status = res.status();
+        let detail = match res.json::<ErrorResponse>().await {
+            Ok(ErrorResponse { detail }) => detail,
+            Err(_) => status.canonical_reason().unwrap_or_default().to_string(),
+        };
+        TwitterApiError::Status { status, detail }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            TwitterApiError::Status { status, .. } => Some(*status),
+            TwitterApiError::MissingData => None,
+            TwitterApiError::Request(e) => e.status(),
+        }
+    }
+
+    pub fn is_unauthorized(&self) -> bool {
+        self.status() == Some(StatusCode::UNAUTHORIZED)
+    }
+}
@@ -0,0 +1,181 @@
+use std::str::FromStr;
+
+use anyhow::{bail, Error, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::twitter::Tweet;
+
+/// ツイートを拾うかどうかのルールの種類
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterRuleKind {
+    /// 指定した著者のツイートだけを拾う。IDかユーザー名
+    AuthorAllow,
+    /// 指定した著者のツイートを拾わない。IDかユーザー名
+    AuthorDeny,
+    /// 本文にキーワードを含むツイートだけを拾う
+    KeywordAllow,
+    /// 本文にキーワードを含むツイートを拾わない
+    KeywordDeny,
+    /// ハッシュタグが付いたツイートだけを拾う
+    HashtagAllow,
+    /// ハッシュタグが付いたツイートを拾わない
+    HashtagDeny,
+    /// 自分のツイートを拾わない
+    ExcludeOwn,
+    /// リツイートを拾わない
+    ExcludeRetweets,
+    /// 他のユーザーへのリプライを拾わない。自分へのリプライ (スレッド) は拾う
+    ExcludeReplies,
+}
+
+impl FilterRuleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FilterRuleKind::AuthorAllow => "author_allow",
+            FilterRuleKind::AuthorDeny => "author_deny",
+            FilterRuleKind::KeywordAllow => "keyword_allow",
+            FilterRuleKind::KeywordDeny => "keyword_deny",
+            FilterRuleKind::HashtagAllow => "hashtag_allow",
+            FilterRuleKind::HashtagDeny => "hashtag_deny",
+            FilterRuleKind::ExcludeOwn => "exclude_own",
+            FilterRuleKind::ExcludeRetweets => "exclude_retweets",
+            FilterRuleKind::ExcludeReplies => "exclude_replies",
+        }
+    }
+
+    /// `value` が必要か
+    pub fn needs_value(&self) -> bool {
+        !matches!(
+            self,
+            FilterRuleKind::ExcludeOwn
+                | FilterRuleKind::ExcludeRetweets
+                | FilterRuleKind::ExcludeReplies
+        )
+    }
+}
+
+impl FromStr for FilterRuleKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<FilterRuleKind> {
+        let kind = match s {
+            "author_allow" => FilterRuleKind::AuthorAllow,
+            "author_deny" => FilterRuleKind::AuthorDeny,
+            "keyword_allow" => FilterRuleKind::KeywordAllow,
+            "keyword_deny" => FilterRuleKind::KeywordDeny,
+            "hashtag_allow" => FilterRuleKind::HashtagAllow,
+            "hashtag_deny" => FilterRuleKind::HashtagDeny,
+            "exclude_own" => FilterRuleKind::ExcludeOwn,
+            "exclude_retweets" => FilterRuleKind::ExcludeRetweets,
+            "exclude_replies" => FilterRuleKind::ExcludeReplies,
+            _ => bail!("Unknown filter rule {s}"),
+        };
+        Ok(kind)
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug)]
+pub struct FilterRule {
+    pub kind: FilterRuleKind,
+    #[serde(default)]
+    pub value: String,
+}
+
+/// 値が必要なルールに値がない
+#[derive(thiserror::Error, Debug)]
+#[error("{} needs a value", .kind.as_str())]
+pub struct InvalidFilterRule {
+    pub kind: FilterRuleKind,
+}
+
+impl FilterRule {
+    /// 前後の空白と `@` `#` を取り除く。`value` が要らないものは空にする
+    pub fn normalize(self) -> Result<FilterRule> {
+        let FilterRule { kind, value } = self;
+        if !kind.needs_value() {
+            return Ok(FilterRule {
+                kind,
+                value: String::new(),
+            });
+        }
+        let value = value.trim();
+        let value = match kind {
+            FilterRuleKind::AuthorAllow | FilterRuleKind::AuthorDeny => {
+                value.trim_start_matches('@')
+            }
+            FilterRuleKind::HashtagAllow | FilterRuleKind::HashtagDeny => {
+                value.trim_start_matches('#')
+            }
+            _ => value,
+        };
+        if value.is_empty() {
+            bail!(InvalidFilterRule { kind });
+        }
+        Ok(FilterRule {
+            kind,
+            value: value.to_string(),
+        })
+    }
+
+    fn matches(&self, tweet: &Tweet, me: u64) -> bool {
+        match self.kind {
+            FilterRuleKind::AuthorAllow | FilterRuleKind::AuthorDeny => {
+                self.value == tweet.author_id.to_string()
+                    || self.value.eq_ignore_ascii_case(&tweet.username)
+            }
+            FilterRuleKind::KeywordAllow | FilterRuleKind::KeywordDeny => tweet
+                .text
+                .to_lowercase()
+                .contains(&self.value.to_lowercase()),
+            FilterRuleKind::HashtagAllow | FilterRuleKind::HashtagDeny => tweet
+                .hashtags
+                .iter()
+                .any(|tag| tag.to_lowercase() == self.value.to_lowercase()),
+            FilterRuleKind::ExcludeOwn => tweet.author_id == me,
+            FilterRuleKind::ExcludeRetweets => tweet.is_retweet(),
+            FilterRuleKind::ExcludeReplies => {
+                matches!(tweet.in_reply_to_user_id, Some(user_id) if user_id != tweet.author_id)
+            }
+        }
+    }
+}
+
+/// ユーザーが設定したルールでツイートを選ぶ
+///
+/// 除外するルールに一つでも当てはまれば拾わない。許可するルールは著者、キーワード、
+/// ハッシュタグそれぞれで、設定されていればどれか一つに当てはまらなければ拾わない。
+#[derive(Clone, Default, Debug)]
+pub struct TweetFilter {
+    rules: Vec<FilterRule>,
+}
+
+impl TweetFilter {
+    pub fn new(rules: Vec<FilterRule>) -> TweetFilter {
+        TweetFilter { rules }
+    }
+
+    /// `me` は連携しているユーザーのID
+    pub fn accepts(&self, tweet: &Tweet, me: u64) -> bool {
+        let allowed = |kind: FilterRuleKind| {
+            let mut rules = self
+                .rules
+                .iter()
+                .filter(|rule| rule.kind == kind)
+                .peekable();
+            rules.peek().is_none() || rules.any(|rule| rule.matches(tweet, me))
+        };
+        let denied = self.rules.iter().any(|rule| {
+            !matches!(
+                rule.kind,
+                FilterRuleKind::AuthorAllow
+                    | FilterRuleKind::KeywordAllow
+                    | FilterRuleKind::HashtagAllow
+            ) && rule.matches(tweet, me)
+        });
+        !denied
+            && allowed(FilterRuleKind::AuthorAllow)
+            && allowed(FilterRuleKind::KeywordAllow)
+            && allowed(FilterRuleKind::HashtagAllow)
+    }
+}
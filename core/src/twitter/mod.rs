@@ -1,7 +1,16 @@
 mod auth;
 mod client;
+mod error;
+mod filter;
+mod source;
 
 pub use self::{
     auth::TwitterOAuth2Client,
-    client::{GetTimeline, TimelineReader, Tweet}
+    client::{
+        GetTimeline, Payload, TimelineMeta, TimelineReader, TimelineTruncated, Tweet,
+        TweetReference, TweetReferenceKind, TwitterClient, TWITTER_API_BASE_URL,
+    },
+    error::TwitterApiError,
+    filter::{FilterRule, FilterRuleKind, InvalidFilterRule, TweetFilter},
+    source::TweetSource,
 };
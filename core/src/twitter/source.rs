@@ -0,0 +1,79 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// どこからツイートを集めるか
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Default, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TweetSource {
+    /// ホームタイムライン
+    #[default]
+    Home,
+    /// 指定したリスト
+    List { id: String },
+    /// いいねしたツイート
+    Likes,
+    /// ブックマーク
+    Bookmarks,
+    /// 指定したユーザーのツイート
+    User { id: String },
+}
+
+impl TweetSource {
+    /// `twitter_accounts.source_kind` と `source_id` から作る
+    pub fn from_parts(kind: &str, id: Option<&str>) -> Result<TweetSource> {
+        let source = match (kind, id) {
+            ("home", _) => TweetSource::Home,
+            ("list", Some(id)) => TweetSource::List { id: id.to_string() },
+            ("likes", _) => TweetSource::Likes,
+            ("bookmarks", _) => TweetSource::Bookmarks,
+            ("user", Some(id)) => TweetSource::User { id: id.to_string() },
+            _ => bail!("Unknown tweet source {kind}"),
+        };
+        Ok(source)
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TweetSource::Home => "home",
+            TweetSource::List { .. } => "list",
+            TweetSource::Likes => "likes",
+            TweetSource::Bookmarks => "bookmarks",
+            TweetSource::User { .. } => "user",
+        }
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        match self {
+            TweetSource::List { id } | TweetSource::User { id } => Some(id),
+            _ => None,
+        }
+    }
+
+    /// IDが数字でなければエラー
+    pub fn validate(&self) -> Result<()> {
+        if let Some(id) = self.id() {
+            if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+                bail!("Invalid id {id}");
+            }
+        }
+        Ok(())
+    }
+
+    /// APIのパス。`me` は連携しているユーザーのID
+    pub fn path(&self, me: u64) -> String {
+        match self {
+            TweetSource::Home => format!("users/{me}/timelines/reverse_chronological"),
+            TweetSource::List { id } => format!("lists/{id}/tweets"),
+            TweetSource::Likes => format!("users/{me}/liked_tweets"),
+            TweetSource::Bookmarks => format!("users/{me}/bookmarks"),
+            TweetSource::User { id } => format!("users/{id}/tweets"),
+        }
+    }
+
+    /// `since_id` で新しいツイートだけを取得できるか
+    ///
+    /// いいねとブックマークはツイートのIDの順に並ばないし、リストは `since_id` を受け付けない。
+    pub fn supports_since_id(&self) -> bool {
+        matches!(self, TweetSource::Home | TweetSource::User { .. })
+    }
+}
@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use chrono::Utc;
+use entity::oauth2_verifier;
+use sea_orm::{
+    prelude::DateTimeWithTimeZone, ActiveModelTrait, ColumnTrait, DatabaseConnection, EntityTrait,
+    QueryFilter, Set,
+};
+use serde::Deserialize;
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum OAuth2Provider {
+    Spotify,
+    Twitter,
+}
+
+impl OAuth2Provider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OAuth2Provider::Spotify => "spotify",
+            OAuth2Provider::Twitter => "twitter",
+        }
+    }
+}
+
+/// 認可の途中で保存しておくPKCEのverifierの設定
+#[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
+#[serde(default)]
+pub struct OAuth2VerifierConfig {
+    /// 認可を始めてからコールバックされるまでの猶予 (秒)
+    pub ttl_secs: u64,
+    /// 期限切れのverifierを消す間隔 (秒)
+    pub purge_interval_secs: u64,
+}
+
+impl Default for OAuth2VerifierConfig {
+    fn default() -> OAuth2VerifierConfig {
+        OAuth2VerifierConfig {
+            ttl_secs: 10 * 60,
+            purge_interval_secs: 10 * 60,
+        }
+    }
+}
+
+/// `oauth2_verifiers` テーブルに `state` とverifierを保存する
+#[derive(Clone, Debug)]
+pub struct OAuth2Verifiers {
+    connection: DatabaseConnection,
+    provider: OAuth2Provider,
+    ttl: Duration,
+}
+
+impl OAuth2Verifiers {
+    pub fn new(
+        connection: DatabaseConnection,
+        provider: OAuth2Provider,
+        config: &OAuth2VerifierConfig,
+    ) -> OAuth2Verifiers {
+        OAuth2Verifiers {
+            connection,
+            provider,
+            ttl: Duration::from_secs(config.ttl_secs),
+        }
+    }
+
+    /// これより前に作られたものは期限切れ
+    fn expired_before(&self) -> DateTimeWithTimeZone {
+        let ttl = chrono::Duration::from_std(self.ttl).unwrap_or_else(|_| chrono::Duration::zero());
+        (Utc::now() - ttl).into()
+    }
+
+    /// `user_id` は認可を始めたユーザー
+    pub async fn insert(
+        &self,
+        state: String,
+        verifier: String,
+        user_id: Option<i32>,
+    ) -> Result<()> {
+        oauth2_verifier::ActiveModel {
+            state: Set(state),
+            verifier: Set(verifier),
+            provider: Set(self.provider.as_str().to_string()),
+            user_id: Set(user_id),
+            created_at: Set(Utc::now().into()),
+        }
+        .insert(&self.connection)
+        .await?;
+        Ok(())
+    }
+
+    /// 一度しか使えないように取り出したら消す
+    ///
+    /// 同じ `state` で同時にコールバックされても、消せた方だけが使える。
+    pub async fn remove(&self, state: &str) -> Result<oauth2_verifier::Model> {
+        let verifier = oauth2_verifier::Entity::find_by_id(state.to_string())
+            .filter(oauth2_verifier::Column::Provider.eq(self.provider.as_str()))
+            .one(&self.connection)
+            .await?;
+        let Some(verifier) = verifier else {
+            bail!("Verifier is none");
+        };
+        let result = oauth2_verifier::Entity::delete_many()
+            .filter(oauth2_verifier::Column::State.eq(state))
+            .filter(oauth2_verifier::Column::Provider.eq(self.provider.as_str()))
+            .exec(&self.connection)
+            .await?;
+        if result.rows_affected != 1 {
+            bail!("Verifier is already used");
+        }
+        if verifier.created_at < self.expired_before() {
+            bail!("Verifier is expired");
+        }
+        Ok(verifier)
+    }
+
+    /// 期限切れのものを消す
+    pub async fn purge(&self) -> Result<u64> {
+        let result = oauth2_verifier::Entity::delete_many()
+            .filter(oauth2_verifier::Column::Provider.eq(self.provider.as_str()))
+            .filter(oauth2_verifier::Column::CreatedAt.lt(self.expired_before()))
+            .exec(&self.connection)
+            .await?;
+        Ok(result.rows_affected)
+    }
+
+    /// `interval` ごとに [`OAuth2Verifiers::purge`] を行う
+    pub async fn run_purge(self, interval: Duration) {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.purge().await {
+                eprintln!("{e}");
+            }
+        }
+    }
+}
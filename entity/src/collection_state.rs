@@ -0,0 +1,34 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "collection_states")]
+pub struct Model {
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub twitter_account_id: String, // TwitterAccount::UserId
+    pub newest_tweet_id: Option<String>, // ここまで読んだツイート
+    pub last_run_at: Option<DateTimeWithTimeZone>,
+    pub last_success_at: Option<DateTimeWithTimeZone>,
+    pub consecutive_failures: i32,
+    #[sea_orm(column_type = "Text", nullable)]
+    pub last_error: Option<String>,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::twitter_account::Entity",
+        from = "Column::TwitterAccountId",
+        to = "super::twitter_account::Column::UserId"
+    )]
+    TwitterAccount,
+}
+
+impl Related<super::twitter_account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TwitterAccount.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -1,3 +1,8 @@
 pub mod user;
 pub mod spotify_account;
 pub mod twitter_account;
+pub mod track;
+pub mod session;
+pub mod oauth2_verifier;
+pub mod collection_state;
+pub mod filter_rule;
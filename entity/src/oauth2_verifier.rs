@@ -0,0 +1,22 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "oauth2_verifiers")]
+pub struct Model {
+    /// 認可リクエストの `state`
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub state: String,
+    /// PKCEのcode verifier
+    pub verifier: String,
+    /// `spotify` か `twitter`
+    pub provider: String,
+    /// 認可を始めたユーザー。Spotifyでのログインのときは `None`
+    pub user_id: Option<i32>, // User::Id
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -8,9 +8,13 @@ pub struct Model {
     pub user_id: String,
     pub display_name: String,
     pub avatar_url: String,
+    #[serde(skip_serializing)]
     pub access_token: String,
+    #[serde(skip_serializing)]
     pub refresh_token: String,
-    pub owner_user_id: i32, // User::Id
+    pub expires_at: Option<DateTimeWithTimeZone>,
+    pub owner_user_id: i32,          // User::Id
+    pub playlist_id: Option<String>, // 収集した楽曲を追加するプレイリスト
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -0,0 +1,41 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, DeriveEntityModel, Deserialize, Serialize)]
+#[sea_orm(table_name = "tracks")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    #[sea_orm(auto_increment)]
+    pub id: i32,
+    pub owner_user_id: i32, // User::Id
+    pub track_uri: String,
+    pub track_url: String,
+    pub source_url: String,
+    pub tweet_id: String,
+    pub tweet_author_id: String,
+    pub tweet_author_username: String,
+    pub tweet_author_name: Option<String>,
+    pub tweet_created_at: Option<DateTimeWithTimeZone>,
+    pub quoted_tweet_id: Option<String>, // 引用したツイートから拾ったとき
+    #[sea_orm(column_type = "Text", nullable)]
+    pub tweet_media_urls: Option<String>, // 添付された画像や動画のURLのJSONの配列
+    pub created_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::user::Entity",
+        from = "Column::OwnerUserId",
+        to = "super::user::Column::Id"
+    )]
+    User,
+}
+
+impl Related<super::user::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::User.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
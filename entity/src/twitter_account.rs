@@ -9,9 +9,16 @@ pub struct Model {
     pub screen_name: String,
     pub display_name: String,
     pub avatar_url: String,
+    #[serde(skip_serializing)]
     pub access_token: String,
+    #[serde(skip_serializing)]
     pub refresh_token: String,
+    pub expires_at: Option<DateTimeWithTimeZone>,
     pub owner_user_id: i32, // User::Id
+    /// ツイートを集めるところ。`home` `list` `likes` `bookmarks` `user` のどれか
+    pub source_kind: String,
+    /// `list` と `user` のときのID
+    pub source_id: Option<String>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -24,6 +31,8 @@ pub enum Relation {
         to = "super::user::Column::Id"
     )]
     User,
+    #[sea_orm(has_one = "super::collection_state::Entity")]
+    CollectionState,
 }
 
 impl Related<super::user::Entity> for Entity {
@@ -32,4 +41,10 @@ impl Related<super::user::Entity> for Entity {
     }
 }
 
+impl Related<super::collection_state::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::CollectionState.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
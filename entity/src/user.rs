@@ -20,6 +20,34 @@ pub enum Relation {
     SpotifyAccount,
     #[sea_orm(has_many = "super::twitter_account::Entity")]
     TwitterAccount,
+    #[sea_orm(has_many = "super::track::Entity")]
+    Track,
+    #[sea_orm(has_many = "super::filter_rule::Entity")]
+    FilterRule,
+}
+
+impl Related<super::spotify_account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::SpotifyAccount.def()
+    }
+}
+
+impl Related<super::twitter_account::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TwitterAccount.def()
+    }
+}
+
+impl Related<super::track::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::Track.def()
+    }
+}
+
+impl Related<super::filter_rule::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::FilterRule.def()
+    }
 }
 
 impl ActiveModelBehavior for ActiveModel {}
@@ -2,6 +2,17 @@ pub use sea_orm_migration::prelude::*;
 
 mod m20220101_000001_create_table;
 mod m20230120_220301_oauth2_account_tables;
+mod m20230204_161500_create_tracks_table;
+mod m20230206_210000_add_expires_at_to_oauth2_accounts;
+mod m20230211_143000_add_playlist_id_to_spotify_accounts;
+mod m20230218_120000_create_sessions_table;
+mod m20230219_100000_create_oauth2_verifiers_table;
+mod m20230225_150000_create_collection_states_table;
+mod m20230226_110000_add_run_state_to_collection_states;
+mod m20230304_120000_add_source_to_twitter_accounts;
+mod m20230311_120000_create_filter_rules_table;
+mod m20230318_120000_add_tweet_metadata_to_tracks;
+mod m20230325_120000_add_tweet_media_urls_to_tracks;
 
 pub struct Migrator;
 
@@ -11,6 +22,17 @@ impl MigratorTrait for Migrator {
         vec![
             Box::new(m20220101_000001_create_table::Migration),
             Box::new(m20230120_220301_oauth2_account_tables::Migration),
+            Box::new(m20230204_161500_create_tracks_table::Migration),
+            Box::new(m20230206_210000_add_expires_at_to_oauth2_accounts::Migration),
+            Box::new(m20230211_143000_add_playlist_id_to_spotify_accounts::Migration),
+            Box::new(m20230218_120000_create_sessions_table::Migration),
+            Box::new(m20230219_100000_create_oauth2_verifiers_table::Migration),
+            Box::new(m20230225_150000_create_collection_states_table::Migration),
+            Box::new(m20230226_110000_add_run_state_to_collection_states::Migration),
+            Box::new(m20230304_120000_add_source_to_twitter_accounts::Migration),
+            Box::new(m20230311_120000_create_filter_rules_table::Migration),
+            Box::new(m20230318_120000_add_tweet_metadata_to_tracks::Migration),
+            Box::new(m20230325_120000_add_tweet_media_urls_to_tracks::Migration),
         ]
     }
 }
@@ -0,0 +1,81 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Tracks::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Tracks::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Tracks::OwnerUserId).integer().not_null())
+                    .col(ColumnDef::new(Tracks::TrackUri).string().not_null())
+                    .col(ColumnDef::new(Tracks::TrackUrl).string().not_null())
+                    .col(ColumnDef::new(Tracks::SourceUrl).string().not_null())
+                    .col(ColumnDef::new(Tracks::TweetId).string().not_null())
+                    .col(ColumnDef::new(Tracks::TweetAuthorId).string().not_null())
+                    .col(
+                        ColumnDef::new(Tracks::TweetAuthorUsername)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(Tracks::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(Tracks::Table, Tracks::OwnerUserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_tracks_owner_user_id_track_uri")
+                    .table(Tracks::Table)
+                    .col(Tracks::OwnerUserId)
+                    .col(Tracks::TrackUri)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(Tracks::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum Tracks {
+    Table,
+    Id,
+    OwnerUserId,
+    TrackUri,
+    TrackUrl,
+    SourceUrl,
+    TweetId,
+    TweetAuthorId,
+    TweetAuthorUsername,
+    CreatedAt,
+}
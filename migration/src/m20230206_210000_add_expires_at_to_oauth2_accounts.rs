@@ -0,0 +1,63 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SpotifyAccounts::Table)
+                    .add_column(
+                        ColumnDef::new(SpotifyAccounts::ExpiresAt).timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TwitterAccounts::Table)
+                    .add_column(
+                        ColumnDef::new(TwitterAccounts::ExpiresAt).timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(SpotifyAccounts::Table)
+                    .drop_column(SpotifyAccounts::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(TwitterAccounts::Table)
+                    .drop_column(TwitterAccounts::ExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum SpotifyAccounts {
+    Table,
+    ExpiresAt,
+}
+
+#[derive(Iden)]
+enum TwitterAccounts {
+    Table,
+    ExpiresAt,
+}
@@ -0,0 +1,69 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(OAuth2Verifiers::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(OAuth2Verifiers::State)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(OAuth2Verifiers::Verifier)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(OAuth2Verifiers::Provider)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(OAuth2Verifiers::UserId).integer())
+                    .col(
+                        ColumnDef::new(OAuth2Verifiers::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_oauth2_verifiers_created_at")
+                    .table(OAuth2Verifiers::Table)
+                    .col(OAuth2Verifiers::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(OAuth2Verifiers::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum OAuth2Verifiers {
+    #[iden = "oauth2_verifiers"]
+    Table,
+    State,
+    Verifier,
+    Provider,
+    UserId,
+    CreatedAt,
+}
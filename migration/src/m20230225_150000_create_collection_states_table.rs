@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20230120_220301_oauth2_account_tables::TwitterAccounts;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(CollectionStates::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(CollectionStates::TwitterAccountId)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(CollectionStates::NewestTweetId).string())
+                    .col(
+                        ColumnDef::new(CollectionStates::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(CollectionStates::Table, CollectionStates::TwitterAccountId)
+                            .to(TwitterAccounts::Table, TwitterAccounts::UserId),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(CollectionStates::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum CollectionStates {
+    Table,
+    TwitterAccountId,
+    NewestTweetId,
+    UpdatedAt,
+}
@@ -0,0 +1,67 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(CollectionStates::LastRunAt)
+                .timestamp_with_time_zone()
+                .to_owned(),
+            ColumnDef::new(CollectionStates::LastSuccessAt)
+                .timestamp_with_time_zone()
+                .to_owned(),
+            ColumnDef::new(CollectionStates::ConsecutiveFailures)
+                .integer()
+                .not_null()
+                .default(0)
+                .to_owned(),
+            ColumnDef::new(CollectionStates::LastError)
+                .text()
+                .to_owned(),
+        ];
+        // SQLiteは1回に1カラムしか追加できない
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(CollectionStates::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            CollectionStates::LastRunAt,
+            CollectionStates::LastSuccessAt,
+            CollectionStates::ConsecutiveFailures,
+            CollectionStates::LastError,
+        ];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(CollectionStates::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum CollectionStates {
+    Table,
+    LastRunAt,
+    LastSuccessAt,
+    ConsecutiveFailures,
+    LastError,
+}
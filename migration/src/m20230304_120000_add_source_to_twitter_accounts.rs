@@ -0,0 +1,53 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(TwitterAccounts::SourceKind)
+                .string()
+                .not_null()
+                .default("home")
+                .to_owned(),
+            ColumnDef::new(TwitterAccounts::SourceId)
+                .string()
+                .to_owned(),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(TwitterAccounts::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [TwitterAccounts::SourceKind, TwitterAccounts::SourceId];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(TwitterAccounts::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum TwitterAccounts {
+    Table,
+    SourceKind,
+    SourceId,
+}
@@ -0,0 +1,72 @@
+use sea_orm_migration::prelude::*;
+
+use crate::m20220101_000001_create_table::Users;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(FilterRules::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FilterRules::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(FilterRules::OwnerUserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(FilterRules::Kind).string().not_null())
+                    .col(ColumnDef::new(FilterRules::Value).string().not_null())
+                    .col(
+                        ColumnDef::new(FilterRules::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .from(FilterRules::Table, FilterRules::OwnerUserId)
+                            .to(Users::Table, Users::Id),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_filter_rules_owner_user_id")
+                    .table(FilterRules::Table)
+                    .col(FilterRules::OwnerUserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FilterRules::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(Iden)]
+pub enum FilterRules {
+    Table,
+    Id,
+    OwnerUserId,
+    Kind,
+    Value,
+    CreatedAt,
+}
@@ -0,0 +1,55 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            ColumnDef::new(Tracks::TweetAuthorName).string().to_owned(),
+            ColumnDef::new(Tracks::TweetCreatedAt)
+                .timestamp_with_time_zone()
+                .to_owned(),
+            ColumnDef::new(Tracks::QuotedTweetId).string().to_owned(),
+        ];
+        for mut column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Tracks::Table)
+                        .add_column(&mut column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let columns = [
+            Tracks::TweetAuthorName,
+            Tracks::TweetCreatedAt,
+            Tracks::QuotedTweetId,
+        ];
+        for column in columns {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(Tracks::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum Tracks {
+    Table,
+    TweetAuthorName,
+    TweetCreatedAt,
+    QuotedTweetId,
+}
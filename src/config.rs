@@ -5,8 +5,12 @@ use std::{
     path::Path,
 };
 
-use anyhow::Result;
-use core::OAuth2ClientCredentials;
+use anyhow::{ensure, Result};
+use api::SessionConfig;
+use core::{
+    CollectorConfig, HttpConfig, OAuth2ClientCredentials, OAuth2VerifierConfig, PurgerConfig,
+    RetryPolicy,
+};
 use serde::Deserialize;
 
 #[derive(Deserialize, PartialEq, Eq, Clone, Debug)]
@@ -15,6 +19,18 @@ pub struct MikageConfig {
     pub addr: SocketAddr,
     pub db: String,
     pub secret: String,
+    #[serde(default)]
+    pub collector: CollectorConfig,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub session: SessionConfig,
+    #[serde(default)]
+    pub verifier: OAuth2VerifierConfig,
+    #[serde(default)]
+    pub purger: PurgerConfig,
 }
 
 impl MikageConfig {
@@ -23,7 +39,28 @@ impl MikageConfig {
         let mut reader = BufReader::new(file);
         let mut buffer = Vec::with_capacity(512);
         reader.read_to_end(&mut buffer)?;
-        let config = toml::from_slice(&buffer)?;
+        let config: MikageConfig = toml::from_slice(&buffer)?;
+        config.validate()?;
         Ok(config)
     }
+
+    /// 間隔が0だと `tokio::time::interval` がpanicしてジョブが止まるので起動時に弾く
+    fn validate(&self) -> Result<()> {
+        let intervals = [
+            ("collector.interval_secs", self.collector.interval_secs),
+            ("purger.interval_secs", self.purger.interval_secs),
+            (
+                "verifier.purge_interval_secs",
+                self.verifier.purge_interval_secs,
+            ),
+            (
+                "session.cleanup_interval_secs",
+                self.session.cleanup_interval_secs,
+            ),
+        ];
+        for (name, secs) in intervals {
+            ensure!(secs > 0, "{name} must be greater than 0");
+        }
+        Ok(())
+    }
 }
@@ -1,11 +1,11 @@
 mod config;
 
-use std::path::Path;
+use std::{path::Path, time::Duration};
 
 use anyhow::Result;
 use api::serve;
 use base64::prelude::*;
-use core::AppState;
+use core::{AppState, Collector, Purger};
 use migration::{Migrator, MigratorTrait};
 use sea_orm::Database;
 
@@ -25,8 +25,19 @@ async fn main() -> Result<()> {
 
     Migrator::up(&connection, None).await?;
 
-    let state = AppState::new(connection, config.credentials);
-    serve(&config.addr, state, &secret).await?;
+    let state = AppState::new(
+        connection,
+        config.credentials,
+        config.retry,
+        &config.http,
+        &config.verifier,
+    )?;
+    let purge_interval = Duration::from_secs(config.verifier.purge_interval_secs);
+    tokio::spawn(state.spotify_verifiers.clone().run_purge(purge_interval));
+    tokio::spawn(state.twitter_verifiers.clone().run_purge(purge_interval));
+    tokio::spawn(Collector::new(state.clone(), config.collector).run());
+    tokio::spawn(Purger::new(state.clone(), config.purger).run());
+    serve(&config.addr, state, &secret, &config.session).await?;
 
     Ok(())
 }
@@ -0,0 +1,440 @@
+//! 結合テスト用の偽のSpotify/Twitterサーバーとデータベース
+
+mod spotify;
+mod twitter;
+
+use std::{
+    collections::HashMap,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{ensure, Result};
+use axum::{
+    extract::State,
+    http::{header::AUTHORIZATION, Request, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    Json, Router, Server,
+};
+use axum_sessions::{async_session::MemoryStore, SameSite, SessionLayer};
+use core::{
+    AppState, HttpConfig, OAuth2ClientCredential, OAuth2ClientCredentials, OAuth2VerifierConfig,
+    RetryPolicy,
+};
+use migration::{Migrator, MigratorTrait};
+use reqwest::{cookie::Jar, redirect};
+use sea_orm::{Database, DatabaseConnection};
+use serde_json::{json, Value};
+
+pub const SPOTIFY_USER_ID: &str = "fake-spotify-user";
+pub const TWITTER_USER_ID: &str = "1000";
+pub const TWITTER_USERNAME: &str = "fake_twitter_user";
+/// 偽のツイートはどれもこの時刻に投稿したことにする
+pub const FAKE_TWEET_CREATED_AT: &str = "2023-03-01T12:00:00.000Z";
+/// [`TestApp::with_linked_playlist`] で選択するプレイリスト
+pub const PLAYLIST_ID: &str = "fake-playlist";
+pub const TRACK_ID: &str = "4uLU6hMCjMI75M1A2tKUQC";
+pub const TRACK_URI: &str = "spotify:track:4uLU6hMCjMI75M1A2tKUQC";
+pub const OTHER_TRACK_ID: &str = "0VjIjW4GlUZAMYd2vXMi3b";
+pub const OTHER_TRACK_URI: &str = "spotify:track:0VjIjW4GlUZAMYd2vXMi3b";
+
+/// 偽のTwitterのタイムラインに流すツイート
+#[derive(Clone, Debug)]
+pub struct FakeTweet {
+    pub id: String,
+    pub text: String,
+    pub author_id: String,
+    pub username: String,
+    pub urls: Vec<String>,
+    /// 添付した画像のURL
+    pub media: Vec<String>,
+}
+
+impl FakeTweet {
+    /// `someone` がリンクを付けずに投稿したツイート
+    pub fn new(id: &str) -> FakeTweet {
+        FakeTweet {
+            id: id.to_string(),
+            text: "good song".to_string(),
+            author_id: "3000".to_string(),
+            username: "someone".to_string(),
+            urls: Vec::new(),
+            media: Vec::new(),
+        }
+    }
+
+    pub fn text(mut self, text: &str) -> FakeTweet {
+        self.text = text.to_string();
+        self
+    }
+
+    pub fn author(mut self, author_id: &str, username: &str) -> FakeTweet {
+        self.author_id = author_id.to_string();
+        self.username = username.to_string();
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> FakeTweet {
+        self.urls.push(url.to_string());
+        self
+    }
+
+    /// 画像を添付する
+    pub fn media(mut self, url: &str) -> FakeTweet {
+        self.media.push(url.to_string());
+        self
+    }
+
+    /// 楽曲のリンクを付ける
+    pub fn track(self, track_id: &str) -> FakeTweet {
+        self.url(&format!("https://open.spotify.com/track/{track_id}"))
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct FakeData {
+    /// プレイリストID -> 楽曲のURI
+    pub playlists: HashMap<String, Vec<String>>,
+    /// プレイリストID -> 持ち主。ないものは [`SPOTIFY_USER_ID`] のもの
+    pub playlist_owners: HashMap<String, String>,
+    /// 共同編集できるプレイリストのID
+    pub collaborative_playlists: Vec<String>,
+    /// 楽曲の追加にあと何回成功させるか。`None` なら失敗させない
+    pub remaining_add_tracks: Option<usize>,
+    pub timeline: Vec<FakeTweet>,
+    /// ホーム以外のツイートの一覧。キーは `lists/42/tweets` のようなAPIのパス
+    pub sources: HashMap<String, Vec<FakeTweet>>,
+    /// 引用しているツイートのID -> 引用されたツイート
+    pub quotes: HashMap<String, FakeTweet>,
+    /// ツイートの一覧の1ページに返す件数の上限。`None` なら `max_results` のまま
+    pub max_page_size: Option<usize>,
+    /// 失効させられたTwitterのトークン
+    pub revoked_tokens: Vec<String>,
+    /// `spotify` や `twitter` ごとの、トークンエンドポイントが発行した回数
+    pub issued_tokens: HashMap<String, usize>,
+    /// 期限切れにしたアクセストークン。これを使ったAPIの呼び出しには401を返す
+    pub expired_access_tokens: Vec<String>,
+    /// `GET /me` が返すSpotifyのユーザー。`None` なら [`SPOTIFY_USER_ID`]
+    pub spotify_user_id: Option<String>,
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct FakeState(Arc<Mutex<FakeData>>);
+
+impl FakeState {
+    pub fn lock(&self) -> std::sync::MutexGuard<'_, FakeData> {
+        self.0.lock().expect("fake state is poisoned")
+    }
+}
+
+/// Spotifyのaccounts/Web APIとTwitterのOAuth2/APIのうち、mikageが使うものだけを真似する
+pub struct FakeServer {
+    pub addr: SocketAddr,
+    pub state: FakeState,
+}
+
+impl FakeServer {
+    pub async fn start() -> Result<FakeServer> {
+        let state = FakeState::default();
+        let app = Router::new()
+            .nest("/spotify", spotify::router())
+            .nest("/twitter", twitter::router())
+            .layer(middleware::from_fn_with_state(
+                state.clone(),
+                reject_expired_tokens,
+            ))
+            .with_state(state.clone());
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let server = Server::from_tcp(listener)?.serve(app.into_make_service());
+        tokio::spawn(server);
+        Ok(FakeServer { addr, state })
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// このサーバーに向けた認証情報を作る。`app_addr` はmikage側のアドレス
+    pub fn credentials(&self, app_addr: SocketAddr) -> OAuth2ClientCredentials {
+        OAuth2ClientCredentials {
+            spotify: OAuth2ClientCredential {
+                client_id: "spotify-client-id".to_string(),
+                client_secret: "spotify-client-secret".to_string(),
+                redirect_uri: format!("http://{app_addr}/callback"),
+                auth_url: Some(self.url("/spotify/accounts/authorize")),
+                token_url: Some(self.url("/spotify/accounts/api/token")),
+                revocation_url: None,
+                api_base_url: Some(self.url("/spotify/v1")),
+            },
+            twitter: OAuth2ClientCredential {
+                client_id: "twitter-client-id".to_string(),
+                client_secret: "twitter-client-secret".to_string(),
+                redirect_uri: format!("http://{app_addr}/twitter/callback"),
+                auth_url: Some(self.url("/twitter/i/oauth2/authorize")),
+                token_url: Some(self.url("/twitter/2/oauth2/token")),
+                revocation_url: Some(self.url("/twitter/2/oauth2/revoke")),
+                api_base_url: Some(self.url("/twitter/2")),
+            },
+        }
+    }
+
+    pub fn create_playlist(&self, playlist_id: &str) {
+        self.state
+            .lock()
+            .playlists
+            .insert(playlist_id.to_string(), Vec::new());
+    }
+
+    /// 他のユーザーが持っているプレイリストを作る
+    pub fn create_other_users_playlist(
+        &self,
+        playlist_id: &str,
+        owner_id: &str,
+        collaborative: bool,
+    ) {
+        let mut state = self.state.lock();
+        state.playlists.insert(playlist_id.to_string(), Vec::new());
+        state
+            .playlist_owners
+            .insert(playlist_id.to_string(), owner_id.to_string());
+        if collaborative {
+            state.collaborative_playlists.push(playlist_id.to_string());
+        }
+    }
+
+    /// プレイリストの末尾に足す。URIの書き方は `GET /playlists/{playlist_id}/tracks` の偽物に合わせる
+    pub fn push_playlist_tracks(&self, playlist_id: &str, uris: &[&str]) {
+        self.state
+            .lock()
+            .playlists
+            .entry(playlist_id.to_string())
+            .or_default()
+            .extend(uris.iter().map(ToString::to_string));
+    }
+
+    /// 楽曲の追加に `count` 回成功した後は403を返す
+    pub fn fail_add_tracks_after(&self, count: usize) {
+        self.state.lock().remaining_add_tracks = Some(count);
+    }
+
+    pub fn playlist_tracks(&self, playlist_id: &str) -> Vec<String> {
+        self.state
+            .lock()
+            .playlists
+            .get(playlist_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub fn revoked_tokens(&self) -> Vec<String> {
+        self.state.lock().revoked_tokens.clone()
+    }
+
+    /// これ以降のSpotifyのログインを別のユーザーとして扱う
+    pub fn switch_spotify_user(&self, spotify_user_id: &str) {
+        self.state.lock().spotify_user_id = Some(spotify_user_id.to_string());
+    }
+
+    /// これまでに発行したアクセストークンをすべて期限切れにする
+    pub fn expire_access_tokens(&self) {
+        let mut state = self.state.lock();
+        let expired = state
+            .issued_tokens
+            .iter()
+            .flat_map(|(prefix, count)| (1..=*count).map(|n| token_name(prefix, "access", n)))
+            .collect::<Vec<_>>();
+        state.expired_access_tokens.extend(expired);
+    }
+
+    pub fn push_tweet(&self, tweet: FakeTweet) {
+        self.state.lock().timeline.insert(0, tweet);
+    }
+
+    /// `quoted` を引用したツイートをタイムラインの先頭に足す
+    pub fn push_quote_tweet(&self, tweet: FakeTweet, quoted: FakeTweet) {
+        let mut state = self.state.lock();
+        state.quotes.insert(tweet.id.clone(), quoted);
+        state.timeline.insert(0, tweet);
+    }
+
+    /// ツイートの一覧を `size` 件ずつに分けて返す
+    pub fn limit_page_size(&self, size: usize) {
+        self.state.lock().max_page_size = Some(size);
+    }
+
+    /// `path` の一覧の先頭に足す
+    pub fn push_source_tweet(&self, path: &str, tweet: FakeTweet) {
+        self.state
+            .lock()
+            .sources
+            .entry(path.to_string())
+            .or_default()
+            .insert(0, tweet);
+    }
+}
+
+/// 偽のサーバーに向けたmikageと、それにアクセスするブラウザ代わりのクライアント
+pub struct TestApp {
+    pub addr: SocketAddr,
+    pub state: AppState,
+    pub client: reqwest::Client,
+    /// `client` とクッキーを共有して、リダイレクトを辿らないクライアント
+    no_redirect_client: reqwest::Client,
+}
+
+impl TestApp {
+    pub async fn start(fake: &FakeServer) -> Result<TestApp> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let state = AppState::new(
+            database().await?,
+            fake.credentials(addr),
+            RetryPolicy::default(),
+            &HttpConfig::default(),
+            &OAuth2VerifierConfig::default(),
+        )?;
+        // テストではhttpで繋ぐのでSecureにしない
+        let session_layer = SessionLayer::new(MemoryStore::new(), &[0; 64])
+            .with_secure(false)
+            .with_same_site_policy(SameSite::Lax);
+        let app = api::router(state.clone(), session_layer);
+        tokio::spawn(Server::from_tcp(listener)?.serve(app.into_make_service()));
+        TestApp::with_new_cookies(addr, state)
+    }
+
+    fn with_new_cookies(addr: SocketAddr, state: AppState) -> Result<TestApp> {
+        let jar = Arc::new(Jar::default());
+        let client = reqwest::Client::builder()
+            .cookie_provider(jar.clone())
+            .build()?;
+        let no_redirect_client = reqwest::Client::builder()
+            .cookie_provider(jar)
+            .redirect(redirect::Policy::none())
+            .build()?;
+        Ok(TestApp {
+            addr,
+            state,
+            client,
+            no_redirect_client,
+        })
+    }
+
+    /// 同じmikageに別のブラウザからアクセスする
+    pub fn another_browser(&self) -> Result<TestApp> {
+        TestApp::with_new_cookies(self.addr, self.state.clone())
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{path}", self.addr)
+    }
+
+    /// Spotifyでログインする
+    pub async fn login(&self) -> Result<()> {
+        let res = self.client.get(self.url("/login")).send().await?;
+        let body = res.text().await?;
+        ensure!(body.contains("Logged in as"), "{body}");
+        Ok(())
+    }
+
+    /// Twitterを連携する
+    pub async fn link_twitter(&self) -> Result<()> {
+        let res = self.client.get(self.url("/twitter/login")).send().await?;
+        ensure!(res.status().is_success(), "{}", res.status());
+        Ok(())
+    }
+
+    /// ログインしてTwitterを連携し、[`PLAYLIST_ID`] を追加先に選択したところから始める
+    pub async fn with_linked_playlist(fake: &FakeServer) -> Result<TestApp> {
+        fake.create_playlist(PLAYLIST_ID);
+        let app = TestApp::start(fake).await?;
+        app.login().await?;
+        app.link_twitter().await?;
+        app.client
+            .put(app.url("/api/playlist"))
+            .json(&json!({ "playlist_id": PLAYLIST_ID }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(app)
+    }
+
+    /// リダイレクトを辿らずに、最初のレスポンスを返す
+    pub async fn get_without_redirect(&self, path: &str) -> Result<reqwest::Response> {
+        Ok(self.no_redirect_client.get(self.url(path)).send().await?)
+    }
+
+    pub async fn get_json(&self, path: &str) -> Result<Value> {
+        let res = self.client.get(self.url(path)).send().await?;
+        Ok(res.error_for_status()?.json().await?)
+    }
+}
+
+/// テストごとに使い捨てるデータベース
+pub async fn database() -> Result<DatabaseConnection> {
+    let connection = Database::connect("sqlite::memory:").await?;
+    Migrator::up(&connection, None).await?;
+    Ok(connection)
+}
+
+/// 最初は `twitter-access-token`、2回目からは `twitter-access-token-2` のように番号を付ける
+fn token_name(prefix: &str, kind: &str, n: usize) -> String {
+    if n == 1 {
+        format!("{prefix}-{kind}-token")
+    } else {
+        format!("{prefix}-{kind}-token-{n}")
+    }
+}
+
+/// トークンエンドポイントのレスポンス。呼ばれるたびに新しいトークンを発行する
+fn token_response(state: &FakeState, prefix: &str) -> Json<Value> {
+    let n = {
+        let mut state = state.lock();
+        let count = state.issued_tokens.entry(prefix.to_string()).or_default();
+        *count += 1;
+        *count
+    };
+    Json(json!({
+        "access_token": token_name(prefix, "access", n),
+        "token_type": "bearer",
+        "expires_in": 3600,
+        "refresh_token": token_name(prefix, "refresh", n),
+    }))
+}
+
+/// 期限切れにしたアクセストークンでAPIを呼ばれたら、それぞれのサービスの形で401を返す
+async fn reject_expired_tokens<B>(
+    State(state): State<FakeState>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let expired = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map_or(false, |token| {
+            state
+                .lock()
+                .expired_access_tokens
+                .iter()
+                .any(|expired| expired == token)
+        });
+    if !expired {
+        return next.run(req).await;
+    }
+    let body = if req.uri().path().starts_with("/spotify") {
+        json!({ "error": { "status": 401, "message": "The access token expired" } })
+    } else {
+        json!({ "title": "Unauthorized", "type": "about:blank", "status": 401, "detail": "Unauthorized" })
+    };
+    (StatusCode::UNAUTHORIZED, Json(body)).into_response()
+}
+
+/// 認可画面の代わりに、すぐに `redirect_uri` へ `code` と `state` を付けて戻す
+fn authorize_redirect(query: &HashMap<String, String>, code: &str) -> axum::response::Redirect {
+    let redirect_uri = query.get("redirect_uri").cloned().unwrap_or_default();
+    let state = query.get("state").cloned().unwrap_or_default();
+    axum::response::Redirect::to(&format!("{redirect_uri}?code={code}&state={state}"))
+}
@@ -0,0 +1,224 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Host, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::{authorize_redirect, token_response, FakeData, FakeState, SPOTIFY_USER_ID};
+
+async fn authorize(Query(query): Query<HashMap<String, String>>) -> Redirect {
+    authorize_redirect(&query, "spotify-code")
+}
+
+async fn token(State(state): State<FakeState>) -> Json<Value> {
+    token_response(&state, "spotify")
+}
+
+async fn me(State(state): State<FakeState>) -> Json<Value> {
+    let id = state
+        .lock()
+        .spotify_user_id
+        .clone()
+        .unwrap_or_else(|| SPOTIFY_USER_ID.to_string());
+    Json(json!({
+        "id": id,
+        "display_name": "Fake Spotify User",
+        "images": [],
+    }))
+}
+
+fn playlist_json(data: &FakeData, playlist_id: &str) -> Value {
+    let owner_id = data
+        .playlist_owners
+        .get(playlist_id)
+        .map_or(SPOTIFY_USER_ID, String::as_str);
+    json!({
+        "id": playlist_id,
+        "name": playlist_id,
+        "owner": { "id": owner_id },
+        "collaborative": data.collaborative_playlists.iter().any(|id| id == playlist_id),
+    })
+}
+
+#[derive(Deserialize)]
+struct PageQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+async fn my_playlists(
+    State(state): State<FakeState>,
+    Host(host): Host,
+    Query(query): Query<PageQuery>,
+) -> Json<Value> {
+    let data = state.lock();
+    let mut ids = data.playlists.keys().collect::<Vec<_>>();
+    ids.sort();
+    let limit = query.limit.unwrap_or(20);
+    let offset = query.offset.unwrap_or(0);
+    let items = ids
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|id| playlist_json(&data, id))
+        .collect::<Vec<_>>();
+    let next = (offset + limit < ids.len()).then(|| {
+        format!(
+            "http://{host}/spotify/v1/me/playlists?limit={limit}&offset={}",
+            offset + limit
+        )
+    });
+    Json(json!({
+        "items": items,
+        "next": next,
+        "total": ids.len(),
+        "offset": offset,
+        "limit": limit,
+    }))
+}
+
+#[derive(Deserialize)]
+struct CreatePlaylist {
+    name: String,
+}
+
+async fn create_playlist(
+    State(state): State<FakeState>,
+    Path(user_id): Path<String>,
+    Json(body): Json<CreatePlaylist>,
+) -> Json<Value> {
+    let mut data = state.lock();
+    let id = format!("playlist-{}", data.playlists.len());
+    data.playlists.insert(id.clone(), Vec::new());
+    data.playlist_owners.insert(id.clone(), user_id.clone());
+    Json(json!({
+        "id": id,
+        "name": body.name,
+        "owner": { "id": user_id },
+        "collaborative": false,
+    }))
+}
+
+async fn playlist(
+    State(state): State<FakeState>,
+    Path(playlist_id): Path<String>,
+) -> impl IntoResponse {
+    let data = state.lock();
+    if !data.playlists.contains_key(&playlist_id) {
+        return not_found();
+    }
+    (StatusCode::OK, Json(playlist_json(&data, &playlist_id)))
+}
+
+/// `spotify:episode:` はエピソードとして、空文字列は削除された楽曲として `track: null` で返す
+fn playlist_item_json(uri: &str) -> Value {
+    let track = if uri.is_empty() {
+        Value::Null
+    } else if uri.starts_with("spotify:episode:") {
+        json!({ "type": "episode", "name": uri, "uri": uri })
+    } else {
+        json!({ "type": "track", "name": uri, "uri": uri })
+    };
+    json!({
+        "added_at": "2023-01-01T00:00:00Z",
+        "is_local": false,
+        "track": track,
+    })
+}
+
+async fn playlist_tracks(
+    State(state): State<FakeState>,
+    Host(host): Host,
+    Path(playlist_id): Path<String>,
+    Query(query): Query<PageQuery>,
+) -> impl IntoResponse {
+    let Some(uris) = state.lock().playlists.get(&playlist_id).cloned() else {
+        return not_found();
+    };
+    let limit = query.limit.unwrap_or(100);
+    let offset = query.offset.unwrap_or(0);
+    let items = uris
+        .iter()
+        .skip(offset)
+        .take(limit)
+        .map(|uri| playlist_item_json(uri))
+        .collect::<Vec<_>>();
+    let next = (offset + limit < uris.len()).then(|| {
+        format!(
+            "http://{host}/spotify/v1/playlists/{playlist_id}/tracks?limit={limit}&offset={}",
+            offset + limit
+        )
+    });
+    (
+        StatusCode::OK,
+        Json(json!({
+            "items": items,
+            "next": next,
+            "total": uris.len(),
+            "offset": offset,
+            "limit": limit,
+        })),
+    )
+}
+
+#[derive(Deserialize)]
+struct AddTracks {
+    uris: Vec<String>,
+    position: Option<usize>,
+}
+
+async fn add_tracks(
+    State(state): State<FakeState>,
+    Path(playlist_id): Path<String>,
+    Json(body): Json<AddTracks>,
+) -> impl IntoResponse {
+    let mut data = state.lock();
+    match &mut data.remaining_add_tracks {
+        Some(0) => {
+            let message = "You cannot add tracks to this playlist.";
+            return (
+                StatusCode::FORBIDDEN,
+                Json(json!({ "error": { "status": 403, "message": message } })),
+            );
+        }
+        Some(remaining) => *remaining -= 1,
+        None => {}
+    }
+    let Some(tracks) = data.playlists.get_mut(&playlist_id) else {
+        return not_found();
+    };
+    let position = body.position.unwrap_or(tracks.len()).min(tracks.len());
+    tracks.splice(position..position, body.uris);
+    let snapshot_id = format!("snapshot-{}", tracks.len());
+    (
+        StatusCode::CREATED,
+        Json(json!({ "snapshot_id": snapshot_id })),
+    )
+}
+
+fn not_found() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::NOT_FOUND,
+        Json(json!({ "error": { "status": 404, "message": "Not found." } })),
+    )
+}
+
+pub fn router() -> Router<FakeState> {
+    Router::new()
+        .route("/accounts/authorize", get(authorize))
+        .route("/accounts/api/token", post(token))
+        .route("/v1/me", get(me))
+        .route("/v1/me/playlists", get(my_playlists))
+        .route("/v1/users/:user_id/playlists", post(create_playlist))
+        .route("/v1/playlists/:playlist_id", get(playlist))
+        .route(
+            "/v1/playlists/:playlist_id/tracks",
+            get(playlist_tracks).post(add_tracks),
+        )
+}
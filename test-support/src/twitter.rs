@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Form, Path, Query, State},
+    response::Redirect,
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    authorize_redirect, token_response, FakeData, FakeState, FakeTweet, FAKE_TWEET_CREATED_AT,
+    TWITTER_USERNAME, TWITTER_USER_ID,
+};
+
+async fn authorize(Query(query): Query<HashMap<String, String>>) -> Redirect {
+    authorize_redirect(&query, "twitter-code")
+}
+
+async fn token(State(state): State<FakeState>) -> Json<Value> {
+    token_response(&state, "twitter")
+}
+
+async fn revoke(
+    State(state): State<FakeState>,
+    Form(form): Form<HashMap<String, String>>,
+) -> Json<Value> {
+    if let Some(token) = form.get("token") {
+        state.lock().revoked_tokens.push(token.clone());
+    }
+    Json(json!({ "revoked": true }))
+}
+
+async fn me() -> Json<Value> {
+    Json(json!({
+        "data": {
+            "id": TWITTER_USER_ID,
+            "name": "Fake Twitter User",
+            "username": TWITTER_USERNAME,
+        }
+    }))
+}
+
+fn tweet_json(tweet: &FakeTweet, quoted: Option<&FakeTweet>) -> Value {
+    let urls = tweet
+        .urls
+        .iter()
+        .map(|url| {
+            json!({
+                "start": 0,
+                "end": 23,
+                "url": "https://t.co/fake",
+                "expanded_url": url,
+                "display_url": url,
+            })
+        })
+        .collect::<Vec<_>>();
+    let referenced_tweets = quoted
+        .map(|quoted| vec![json!({ "type": "quoted", "id": quoted.id })])
+        .unwrap_or_default();
+    let media_keys = (0..tweet.media.len())
+        .map(|i| media_key(tweet, i))
+        .collect::<Vec<_>>();
+    json!({
+        "id": tweet.id,
+        "text": tweet.text,
+        "author_id": tweet.author_id,
+        "created_at": FAKE_TWEET_CREATED_AT,
+        "entities": { "urls": urls },
+        "referenced_tweets": referenced_tweets,
+        "attachments": { "media_keys": media_keys },
+    })
+}
+
+fn media_key(tweet: &FakeTweet, i: usize) -> String {
+    format!("3_{}{i}", tweet.id)
+}
+
+fn media_json(tweet: &FakeTweet) -> Vec<Value> {
+    tweet
+        .media
+        .iter()
+        .enumerate()
+        .map(|(i, url)| json!({ "media_key": media_key(tweet, i), "type": "photo", "url": url }))
+        .collect()
+}
+
+fn user_json(tweet: &FakeTweet) -> Value {
+    json!({
+        "id": tweet.author_id,
+        "name": format!("{} (display name)", tweet.username),
+        "username": tweet.username,
+    })
+}
+
+async fn reverse_chronological_timeline(
+    State(state): State<FakeState>,
+    Path(_user_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let state = state.lock();
+    tweets_response(&state.timeline, &state, &query)
+}
+
+async fn list_tweets(
+    State(state): State<FakeState>,
+    Path(list_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    source_tweets(&state, &format!("lists/{list_id}/tweets"), query)
+}
+
+async fn liked_tweets(
+    State(state): State<FakeState>,
+    Path(user_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    source_tweets(&state, &format!("users/{user_id}/liked_tweets"), query)
+}
+
+async fn bookmarks(
+    State(state): State<FakeState>,
+    Path(user_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    source_tweets(&state, &format!("users/{user_id}/bookmarks"), query)
+}
+
+async fn user_tweets(
+    State(state): State<FakeState>,
+    Path(user_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Json<Value> {
+    let state = state.lock();
+    let tweets = state
+        .sources
+        .get(&format!("users/{user_id}/tweets"))
+        .cloned()
+        .unwrap_or_default();
+    tweets_response(&tweets, &state, &query)
+}
+
+/// `since_id` を受け付けない一覧
+fn source_tweets(state: &FakeState, path: &str, mut query: HashMap<String, String>) -> Json<Value> {
+    query.remove("since_id");
+    let state = state.lock();
+    let tweets = state.sources.get(path).cloned().unwrap_or_default();
+    tweets_response(&tweets, &state, &query)
+}
+
+/// `since_id` より新しいものを `max_results` 件ずつ返す。`pagination_token` は何件目から返すか
+fn tweets_response(
+    tweets: &[FakeTweet],
+    state: &FakeData,
+    query: &HashMap<String, String>,
+) -> Json<Value> {
+    let since_id = query.get("since_id").and_then(|id| id.parse::<u64>().ok());
+    let offset = query
+        .get("pagination_token")
+        .and_then(|token| token.parse::<usize>().ok())
+        .unwrap_or(0);
+    let max_results = query
+        .get("max_results")
+        .and_then(|max_results| max_results.parse::<usize>().ok())
+        .unwrap_or(100);
+    let page_size = state
+        .max_page_size
+        .map_or(max_results, |max_page_size| max_results.min(max_page_size));
+    let unread = tweets
+        .iter()
+        .filter(|tweet| match (since_id, tweet.id.parse::<u64>()) {
+            (Some(since_id), Ok(id)) => id > since_id,
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+    let timeline = unread
+        .iter()
+        .skip(offset)
+        .take(page_size)
+        .copied()
+        .cloned()
+        .collect::<Vec<_>>();
+    if timeline.is_empty() {
+        return Json(json!({ "meta": { "result_count": 0 } }));
+    }
+    let next_token = (offset + page_size < unread.len()).then(|| (offset + page_size).to_string());
+    // 引用しているツイートのID -> 引用されたツイート
+    let quotes = &state.quotes;
+    let data = timeline
+        .iter()
+        .map(|tweet| tweet_json(tweet, quotes.get(&tweet.id)))
+        .collect::<Vec<_>>();
+    let quoted = timeline
+        .iter()
+        .flat_map(|tweet| quotes.get(&tweet.id))
+        .collect::<Vec<_>>();
+    let users = timeline
+        .iter()
+        .chain(quoted.iter().copied())
+        .map(user_json)
+        .collect::<Vec<_>>();
+    let included_tweets = quoted
+        .iter()
+        .map(|tweet| tweet_json(tweet, None))
+        .collect::<Vec<_>>();
+    let media = timeline
+        .iter()
+        .chain(quoted.iter().copied())
+        .flat_map(media_json)
+        .collect::<Vec<_>>();
+    Json(json!({
+        "data": data,
+        "includes": { "users": users, "tweets": included_tweets, "media": media },
+        "meta": {
+            "newest_id": timeline.first().map(|tweet| tweet.id.clone()),
+            "oldest_id": timeline.last().map(|tweet| tweet.id.clone()),
+            "result_count": timeline.len(),
+            "next_token": next_token,
+        },
+    }))
+}
+
+pub fn router() -> Router<FakeState> {
+    Router::new()
+        .route("/i/oauth2/authorize", get(authorize))
+        .route("/2/oauth2/token", post(token))
+        .route("/2/oauth2/revoke", post(revoke))
+        .route("/2/users/me", get(me))
+        .route(
+            "/2/users/:user_id/timelines/reverse_chronological",
+            get(reverse_chronological_timeline),
+        )
+        .route("/2/lists/:list_id/tweets", get(list_tweets))
+        .route("/2/users/:user_id/liked_tweets", get(liked_tweets))
+        .route("/2/users/:user_id/bookmarks", get(bookmarks))
+        .route("/2/users/:user_id/tweets", get(user_tweets))
+}
@@ -0,0 +1,99 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use core::services::UserService;
+use reqwest::{header::LOCATION, StatusCode};
+use test_support::{FakeServer, TestApp};
+
+/// 退会すると収集が止まり、復帰を指定しなければログインできない
+#[tokio::test]
+async fn delete_and_reactivate_account() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+    app.link_twitter().await?;
+    let me = app.get_json("/api/me").await?;
+
+    let res = app.client.delete(app.url("/api/me")).send().await?;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(fake.revoked_tokens(), vec!["twitter-refresh-token"]);
+    let res = app.client.get(app.url("/api/me")).send().await?;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    let res = app.client.get(app.url("/login")).send().await?;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    let res = app
+        .client
+        .get(app.url("/login?reactivate=true"))
+        .send()
+        .await?;
+    assert!(res.status().is_success(), "{}", res.status());
+    let reactivated = app.get_json("/api/me").await?;
+    assert_eq!(reactivated["id"], me["id"]);
+    assert!(reactivated["deleted_at"].is_null());
+    assert!(reactivated["twitter_account"].is_null());
+
+    Ok(())
+}
+
+/// 別のセッションで退会したら、残っているセッションからも操作できない
+#[tokio::test]
+async fn lock_out_other_sessions() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let other = app.another_browser()?;
+    other.login().await?;
+    let me = app.get_json("/api/me").await?;
+    let other_me = other.get_json("/api/me").await?;
+    assert_eq!(other_me["id"], me["id"]);
+
+    let res = app.client.delete(app.url("/api/me")).send().await?;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+    for path in ["/api/me", "/api/playlist", "/api/source", "/api/filters"] {
+        let res = other.client.get(other.url(path)).send().await?;
+        assert_eq!(res.status(), StatusCode::UNAUTHORIZED, "{path}");
+    }
+    let res = other
+        .client
+        .delete(other.url("/api/me/twitter"))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    let res = other.get_without_redirect("/twitter/login").await?;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    assert_eq!(res.headers()[LOCATION], "/");
+    // ログインし直そうとすると復帰させるかを聞かれる
+    let res = other.client.get(other.url("/login")).send().await?;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+/// 保持期間が過ぎた退会済みのユーザーだけを消す
+#[tokio::test]
+async fn purge_deleted_users() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+    let me = app.get_json("/api/me").await?;
+    let user_id = me["id"].as_i64().expect("user id") as i32;
+
+    let service = UserService::new(app.state.clone());
+    service.delete_account(user_id).await?;
+    assert_eq!(
+        service
+            .purge_deleted(Utc::now() - Duration::days(1))
+            .await?,
+        0
+    );
+    assert_eq!(
+        service
+            .purge_deleted(Utc::now() + Duration::days(1))
+            .await?,
+        1
+    );
+    assert!(service.find_profile(user_id).await?.is_none());
+
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use core::{Collector, CollectorConfig, DuplicatePolicy};
+use entity::track;
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use test_support::{FakeServer, FakeTweet, TestApp, PLAYLIST_ID, TRACK_ID, TRACK_URI};
+
+/// 最後に拾ってから指定した日数が経つまでは同じ楽曲を追加しない
+#[tokio::test]
+async fn allow_after_days() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let config = CollectorConfig {
+        duplicate_policy: DuplicatePolicy::AllowAfterDays(30),
+        ..CollectorConfig::default()
+    };
+    let collector = Collector::new(app.state.clone(), config);
+
+    fake.push_tweet(FakeTweet::new("2000").track(TRACK_ID));
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    // 期間内
+    fake.push_tweet(FakeTweet::new("2001").track(TRACK_ID));
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    // 期間外
+    let connection = collector.connection();
+    let picked = track::Entity::find()
+        .one(connection)
+        .await?
+        .expect("track is recorded");
+    let mut picked: track::ActiveModel = picked.into();
+    picked.created_at = Set((Utc::now() - Duration::days(31)).into());
+    picked.update(connection).await?;
+    fake.push_tweet(FakeTweet::new("2002").track(TRACK_ID));
+    collector.collect().await?;
+    assert_eq!(
+        fake.playlist_tracks(PLAYLIST_ID),
+        vec![TRACK_URI, TRACK_URI]
+    );
+    assert_eq!(track::Entity::find().all(connection).await?.len(), 2);
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use core::{Collector, CollectorConfig};
+use reqwest::StatusCode;
+use serde_json::json;
+use test_support::{
+    FakeServer, FakeTweet, TestApp, OTHER_TRACK_ID, PLAYLIST_ID, TRACK_ID, TRACK_URI,
+    TWITTER_USERNAME, TWITTER_USER_ID,
+};
+
+/// ルールに当てはまるツイートの楽曲は拾わない
+#[tokio::test]
+async fn filter_rules() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let client = &app.client;
+
+    let res = client
+        .post(app.url("/api/filters"))
+        .json(&json!({ "kind": "keyword_deny", "value": "  " }))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let rules = [
+        json!({ "kind": "author_deny", "value": "@Spammer" }),
+        json!({ "kind": "keyword_deny", "value": "PROMO" }),
+        json!({ "kind": "exclude_own" }),
+    ];
+    for rule in rules {
+        let res = client
+            .post(app.url("/api/filters"))
+            .json(&rule)
+            .send()
+            .await?;
+        assert_eq!(res.status(), StatusCode::CREATED);
+    }
+    let rules = app.get_json("/api/filters").await?;
+    assert_eq!(rules.as_array().map(Vec::len), Some(3));
+    assert_eq!(rules[0]["value"], "Spammer");
+
+    fake.push_tweet(FakeTweet::new("2000").track(TRACK_ID));
+    fake.push_tweet(
+        FakeTweet::new("2001")
+            .author("4000", "spammer")
+            .track(OTHER_TRACK_ID),
+    );
+    fake.push_tweet(
+        FakeTweet::new("2002")
+            .text("promo!")
+            .track("1301WleyT98MSxVHPZCA6M"),
+    );
+    fake.push_tweet(
+        FakeTweet::new("2003")
+            .author(TWITTER_USER_ID, TWITTER_USERNAME)
+            .track("7ouMYWpwJ422jRcDASZB7P"),
+    );
+    let collector = Collector::new(app.state.clone(), CollectorConfig::default());
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    let id = rules[0]["id"].as_i64().expect("rule has an id");
+    let res = client
+        .delete(app.url(&format!("/api/filters/{id}")))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = client
+        .delete(app.url(&format!("/api/filters/{id}")))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -0,0 +1,213 @@
+use anyhow::{ensure, Result};
+use core::{
+    services::{CollectionStateService, TrackService},
+    spotify::ResolveShortLink,
+    Collector, CollectorConfig,
+};
+use entity::{collection_state, user};
+use reqwest::Url;
+use sea_orm::EntityTrait;
+use test_support::{
+    FakeServer, FakeTweet, TestApp, OTHER_TRACK_ID, OTHER_TRACK_URI, PLAYLIST_ID, SPOTIFY_USER_ID,
+    TRACK_ID, TRACK_URI, TWITTER_USER_ID,
+};
+
+/// ログイン -> コールバック -> Twitter連携 -> 収集 を偽のサーバーに対して通す
+#[tokio::test]
+async fn login_link_and_collect() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let state = app.state.clone();
+
+    let me = app.get_json("/api/me").await?;
+    assert_eq!(me["spotify_account"]["user_id"], SPOTIFY_USER_ID);
+    assert_eq!(me["twitter_account"]["user_id"], TWITTER_USER_ID);
+    assert!(me["spotify_account"].get("access_token").is_none());
+    assert!(me["twitter_account"].get("refresh_token").is_none());
+
+    fake.push_tweet(
+        FakeTweet::new("2000")
+            .url("https://open.spotify.com/intl-ja/track/4uLU6hMCjMI75M1A2tKUQC?si=abc"),
+    );
+
+    let collector = Collector::new(state.clone(), CollectorConfig::default());
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    let user = user::Entity::find()
+        .one(collector.connection())
+        .await?
+        .expect("user is created");
+    let tracks = TrackService::new(state.clone())
+        .find_by_user(user.id)
+        .await?;
+    assert_eq!(tracks.len(), 1);
+    assert_eq!(tracks[0].track_uri, TRACK_URI);
+
+    let cursor = CollectionStateService::new(state.clone())
+        .find_cursor(TWITTER_USER_ID)
+        .await?;
+    assert_eq!(cursor.as_deref(), Some("2000"));
+    let me = app.get_json("/api/me").await?;
+    assert_eq!(me["collection_state"]["consecutive_failures"], 0);
+    assert!(!me["collection_state"]["last_success_at"].is_null());
+
+    // 新しいツイートがなくても失敗しない
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+    assert_eq!(
+        TrackService::new(state.clone())
+            .find_by_user(user.id)
+            .await?
+            .len(),
+        1
+    );
+
+    // 前回読んだところより新しいツイートだけを読む
+    fake.push_tweet(
+        FakeTweet::new("2001")
+            .text("another song")
+            .track(OTHER_TRACK_ID),
+    );
+    collector.collect().await?;
+    assert_eq!(
+        fake.playlist_tracks(PLAYLIST_ID),
+        vec![TRACK_URI, OTHER_TRACK_URI]
+    );
+    assert_eq!(
+        TrackService::new(state.clone())
+            .find_by_user(user.id)
+            .await?
+            .len(),
+        2
+    );
+
+    // 同じツイートをもう一度読んでも重複して追加しない
+    collection_state::Entity::delete_by_id(TWITTER_USER_ID.to_string())
+        .exec(collector.connection())
+        .await?;
+    collector.collect().await?;
+    assert_eq!(
+        fake.playlist_tracks(PLAYLIST_ID),
+        vec![TRACK_URI, OTHER_TRACK_URI]
+    );
+
+    // 別のツイートで同じ楽曲を見つけても追加しない
+    fake.push_tweet(
+        FakeTweet::new("2002")
+            .author("3001", "another")
+            .track(TRACK_ID),
+    );
+    collector.collect().await?;
+    assert_eq!(
+        fake.playlist_tracks(PLAYLIST_ID),
+        vec![TRACK_URI, OTHER_TRACK_URI]
+    );
+    assert_eq!(
+        TrackService::new(state.clone())
+            .find_by_user(user.id)
+            .await?
+            .len(),
+        2
+    );
+    let cursor = CollectionStateService::new(state)
+        .find_cursor(TWITTER_USER_ID)
+        .await?;
+    assert_eq!(cursor.as_deref(), Some("2002"));
+
+    Ok(())
+}
+
+/// 前回読んだところまで、複数のページをさかのぼって読む
+#[tokio::test]
+async fn read_pages_back_to_cursor() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let collector = Collector::new(app.state.clone(), CollectorConfig::default());
+    let states = CollectionStateService::new(app.state.clone());
+
+    fake.push_tweet(FakeTweet::new("2000"));
+    collector.collect().await?;
+    let cursor = states.find_cursor(TWITTER_USER_ID).await?;
+    assert_eq!(cursor.as_deref(), Some("2000"));
+
+    // 2件ずつ3ページに分かれる
+    fake.limit_page_size(2);
+    fake.push_tweet(FakeTweet::new("2001").track(TRACK_ID));
+    for id in 2002..=2005 {
+        fake.push_tweet(FakeTweet::new(&id.to_string()));
+    }
+    fake.push_tweet(FakeTweet::new("2006").track(OTHER_TRACK_ID));
+    collector.collect().await?;
+    let tracks = fake.playlist_tracks(PLAYLIST_ID);
+    assert_eq!(tracks.len(), 2);
+    assert!(tracks.contains(&TRACK_URI.to_string()));
+    assert!(tracks.contains(&OTHER_TRACK_URI.to_string()));
+    let cursor = states.find_cursor(TWITTER_USER_ID).await?;
+    assert_eq!(cursor.as_deref(), Some("2006"));
+    let me = app.get_json("/api/me").await?;
+    assert!(me["collection_state"]["last_error"].is_null());
+
+    Ok(())
+}
+
+/// さかのぼれるページ数を超えたら、読んだ分は拾って読み飛ばしたことを記録する
+#[tokio::test]
+async fn record_skipped_pages() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let collector = Collector::new(app.state.clone(), CollectorConfig::default());
+
+    fake.push_tweet(FakeTweet::new("2000"));
+    collector.collect().await?;
+
+    // 1件ずつ10ページあるうちの新しい8ページだけを読む
+    fake.limit_page_size(1);
+    fake.push_tweet(FakeTweet::new("2001").track(TRACK_ID));
+    for id in 2002..=2009 {
+        fake.push_tweet(FakeTweet::new(&id.to_string()));
+    }
+    fake.push_tweet(FakeTweet::new("2010").track(OTHER_TRACK_ID));
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![OTHER_TRACK_URI]);
+    let cursor = CollectionStateService::new(app.state.clone())
+        .find_cursor(TWITTER_USER_ID)
+        .await?;
+    assert_eq!(cursor.as_deref(), Some("2010"));
+    let me = app.get_json("/api/me").await?;
+    assert_eq!(me["collection_state"]["consecutive_failures"], 1);
+    let last_error = me["collection_state"]["last_error"]
+        .as_str()
+        .unwrap_or_default();
+    assert!(last_error.contains("more than 8 new pages"), "{last_error}");
+
+    Ok(())
+}
+
+/// 短縮URLを展開する
+struct StubResolver;
+
+#[async_trait::async_trait]
+impl ResolveShortLink for StubResolver {
+    async fn resolve(&self, url: &Url) -> Result<Url> {
+        ensure!(url.as_str() == "https://spotify.link/abc", "unknown {url}");
+        Ok(Url::parse(&format!(
+            "https://open.spotify.com/track/{TRACK_ID}"
+        ))?)
+    }
+}
+
+/// 差し替えた展開方法で短縮URLの楽曲を拾う
+#[tokio::test]
+async fn collect_short_link() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+
+    fake.push_tweet(FakeTweet::new("2000").url("https://spotify.link/abc"));
+    let collector =
+        Collector::with_resolver(app.state.clone(), CollectorConfig::default(), StubResolver);
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    Ok(())
+}
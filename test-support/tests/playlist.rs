@@ -0,0 +1,117 @@
+use anyhow::Result;
+use entity::{spotify_account, user};
+use reqwest::StatusCode;
+use sea_orm::{EntityTrait, ModelTrait};
+use serde_json::{json, Value};
+use test_support::{FakeServer, TestApp, SPOTIFY_USER_ID};
+
+/// 1ページに収まらない分も辿り、追加できないプレイリストは候補に出さない
+#[tokio::test]
+async fn candidates_include_every_page() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    for i in 0..60 {
+        fake.create_playlist(&format!("mine-{i:02}"));
+    }
+    fake.create_other_users_playlist("others", "other-user", false);
+    fake.create_other_users_playlist("shared", "other-user", true);
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+
+    let candidates = app.get_json("/api/playlist/candidates").await?;
+    let ids = candidates["items"]
+        .as_array()
+        .expect("items is an array")
+        .iter()
+        .map(|playlist| playlist["id"].as_str().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(ids.len(), 61);
+    assert!(ids.contains(&"mine-59"));
+    assert!(ids.contains(&"shared"));
+    assert!(!ids.contains(&"others"));
+    Ok(())
+}
+
+/// 自分のものか共同編集できるものだけを追加先にできる
+#[tokio::test]
+async fn select_only_owned_or_collaborative() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    fake.create_playlist("mine");
+    fake.create_other_users_playlist("others", "other-user", false);
+    fake.create_other_users_playlist("shared", "other-user", true);
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+
+    let select = |playlist_id: &str| {
+        app.client
+            .put(app.url("/api/playlist"))
+            .json(&json!({ "playlist_id": playlist_id }))
+            .send()
+    };
+    assert_eq!(select("others").await?.status(), StatusCode::FORBIDDEN);
+    let current = app.get_json("/api/playlist").await?;
+    assert!(current["playlist"].is_null());
+
+    let res = select("shared").await?;
+    assert!(res.status().is_success(), "{}", res.status());
+    let body: Value = res.json().await?;
+    assert_eq!(body["playlist"]["collaborative"], true);
+
+    let res = select("mine").await?;
+    assert!(res.status().is_success(), "{}", res.status());
+    let current = app.get_json("/api/playlist").await?;
+    assert_eq!(current["playlist"]["id"], "mine");
+    assert_eq!(current["playlist"]["owner"]["id"], SPOTIFY_USER_ID);
+    Ok(())
+}
+
+/// Spotifyの連携がなければ404、ユーザーがいなければ401を返す
+#[tokio::test]
+async fn missing_account_or_user() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+    let connection = &app.state.connection;
+
+    let account = spotify_account::Entity::find()
+        .one(connection)
+        .await?
+        .expect("spotify account is linked");
+    account.delete(connection).await?;
+    let res = app.client.get(app.url("/api/playlist")).send().await?;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = app
+        .client
+        .get(app.url("/api/playlist/candidates"))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let user = user::Entity::find()
+        .one(connection)
+        .await?
+        .expect("user is created");
+    user.delete(connection).await?;
+    let res = app.client.get(app.url("/api/playlist")).send().await?;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    Ok(())
+}
+
+/// 期限内でも401を返されたら、リフレッシュしてもう一度だけ試す
+#[tokio::test]
+async fn refresh_on_unauthorized() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    fake.create_playlist("mine");
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+
+    fake.expire_access_tokens();
+    let candidates = app.get_json("/api/playlist/candidates").await?;
+    assert_eq!(candidates["items"][0]["id"], "mine");
+
+    let account = spotify_account::Entity::find()
+        .one(&app.state.connection)
+        .await?
+        .expect("spotify account is linked");
+    assert_eq!(account.access_token, "spotify-access-token-2");
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use anyhow::Result;
+use core::{spotify::PlaylistTrack, Collector, CollectorConfig};
+use reqwest::StatusCode;
+use test_support::{
+    FakeServer, FakeTweet, TestApp, OTHER_TRACK_ID, OTHER_TRACK_URI, PLAYLIST_ID, TRACK_ID,
+    TRACK_URI,
+};
+
+const EPISODE_URI: &str = "spotify:episode:512ojhOuo1ktJprKbVcKyQ";
+
+/// 100件ずつのページを `next` で辿り、削除された楽曲やエピソードも読める
+#[tokio::test]
+async fn get_every_page() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    let fillers = (0..150)
+        .map(|i| format!("spotify:track:filler{i:03}"))
+        .collect::<Vec<_>>();
+    let mut uris = fillers.iter().map(String::as_str).collect::<Vec<_>>();
+    uris.extend(["", EPISODE_URI, TRACK_URI, "", "spotify:track:last"]);
+    fake.push_playlist_tracks(PLAYLIST_ID, &uris);
+
+    let client = app.state.spotify_client("spotify-access-token".to_string());
+    let items = client.get_playlist_tracks(PLAYLIST_ID).await?;
+    assert_eq!(items.len(), 155);
+    let read = items
+        .iter()
+        .map(|item| item.uri().unwrap_or_default())
+        .collect::<Vec<_>>();
+    assert_eq!(read, uris);
+    assert!(items[150].track.is_none());
+    assert!(matches!(items[151].track, Some(PlaylistTrack::Episode(_))));
+    assert!(matches!(items[152].track, Some(PlaylistTrack::Track(_))));
+    Ok(())
+}
+
+/// 2ページ目以降にある楽曲も重複として扱う
+#[tokio::test]
+async fn skip_tracks_on_later_pages() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let fillers = (0..150)
+        .map(|i| format!("spotify:track:filler{i:03}"))
+        .collect::<Vec<_>>();
+    let mut uris = fillers.iter().map(String::as_str).collect::<Vec<_>>();
+    uris.extend(["", EPISODE_URI, TRACK_URI]);
+    fake.push_playlist_tracks(PLAYLIST_ID, &uris);
+
+    fake.push_tweet(FakeTweet::new("2000").track(TRACK_ID).track(OTHER_TRACK_ID));
+    Collector::new(app.state.clone(), CollectorConfig::default())
+        .collect()
+        .await?;
+    let tracks = fake.playlist_tracks(PLAYLIST_ID);
+    assert_eq!(tracks.len(), 154);
+    assert_eq!(tracks.last().map(String::as_str), Some(OTHER_TRACK_URI));
+    Ok(())
+}
+
+fn new_track_uris(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| format!("spotify:track:new{i:03}"))
+        .collect()
+}
+
+/// 100件ずつに分けて、指定した位置からずらしながら挿入する
+#[tokio::test]
+async fn add_tracks_in_chunks() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    fake.push_playlist_tracks(PLAYLIST_ID, &[TRACK_URI, OTHER_TRACK_URI]);
+    let uris = new_track_uris(250);
+
+    let client = app.state.spotify_client("spotify-access-token".to_string());
+    let added = client
+        .add_tracks_to_playlist(PLAYLIST_ID, uris.clone(), Some(1))
+        .await?;
+    let snapshots = added
+        .iter()
+        .map(|added| added.snapshot_id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(snapshots, ["snapshot-102", "snapshot-202", "snapshot-252"]);
+    assert_eq!(added[0].uris, uris[..100]);
+    assert_eq!(added[2].uris, uris[200..]);
+
+    let mut expected = vec![TRACK_URI.to_string()];
+    expected.extend(uris);
+    expected.push(OTHER_TRACK_URI.to_string());
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), expected);
+    Ok(())
+}
+
+/// 途中で失敗したら残りを送らず、それまでのスナップショットを返す
+#[tokio::test]
+async fn add_tracks_partially() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    fake.create_playlist(PLAYLIST_ID);
+    fake.fail_add_tracks_after(1);
+    let uris = new_track_uris(250);
+
+    let client = app.state.spotify_client("spotify-access-token".to_string());
+    let e = client
+        .add_tracks_to_playlist(PLAYLIST_ID, uris.clone(), None)
+        .await
+        .expect_err("second chunk fails");
+    assert_eq!(e.added.len(), 1);
+    assert_eq!(e.added[0].snapshot_id, "snapshot-100");
+    assert_eq!(e.added_uris(), uris[..100]);
+    assert_eq!(e.failed, uris[100..]);
+    assert_eq!(e.source.status(), Some(StatusCode::FORBIDDEN));
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), uris[..100]);
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use core::{services::TrackService, Collector, CollectorConfig};
+use entity::user;
+use sea_orm::EntityTrait;
+use test_support::{
+    FakeServer, FakeTweet, TestApp, FAKE_TWEET_CREATED_AT, PLAYLIST_ID, TRACK_ID, TRACK_URI,
+};
+
+/// 引用したツイートに含まれるリンクも拾って、どのツイートから拾ったかを記録する
+#[tokio::test]
+async fn collect_from_quoted_tweet() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+
+    fake.push_quote_tweet(
+        FakeTweet::new("2001")
+            .text("this one is great")
+            .media("https://pbs.twimg.com/media/fake.jpg"),
+        FakeTweet::new("1500")
+            .text("new release")
+            .author("4000", "artist")
+            .track(TRACK_ID),
+    );
+    let collector = Collector::new(app.state.clone(), CollectorConfig::default());
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    let user = user::Entity::find()
+        .one(collector.connection())
+        .await?
+        .expect("user is created");
+    let tracks = TrackService::new(app.state.clone())
+        .find_by_user(user.id)
+        .await?;
+    assert_eq!(tracks.len(), 1);
+    assert_eq!(tracks[0].tweet_id, "2001");
+    assert_eq!(tracks[0].quoted_tweet_id.as_deref(), Some("1500"));
+    assert_eq!(
+        tracks[0].tweet_media_urls.as_deref(),
+        Some(r#"["https://pbs.twimg.com/media/fake.jpg"]"#)
+    );
+    assert_eq!(
+        tracks[0].tweet_author_name.as_deref(),
+        Some("someone (display name)")
+    );
+    let created_at = FAKE_TWEET_CREATED_AT.parse::<DateTime<Utc>>()?;
+    assert_eq!(
+        tracks[0].tweet_created_at.map(|t| t.with_timezone(&Utc)),
+        Some(created_at)
+    );
+
+    Ok(())
+}
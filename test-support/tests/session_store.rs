@@ -0,0 +1,43 @@
+use anyhow::Result;
+use api::DatabaseSessionStore;
+use axum_sessions::async_session::{Session, SessionStore};
+use test_support::database;
+
+#[tokio::test]
+async fn store_and_load_session() -> Result<()> {
+    let store = DatabaseSessionStore::new(database().await?);
+
+    let mut session = Session::new();
+    session.insert("user_id", 1)?;
+    let cookie_value = store.store_session(session).await?.expect("cookie value");
+
+    let session = store
+        .load_session(cookie_value.clone())
+        .await?
+        .expect("session is stored");
+    assert_eq!(session.get::<i32>("user_id"), Some(1));
+
+    store.destroy_session(session).await?;
+    assert!(store.load_session(cookie_value).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn cleanup_expired_sessions() -> Result<()> {
+    let store = DatabaseSessionStore::new(database().await?);
+
+    let mut expired = Session::new();
+    expired.expire_in(std::time::Duration::from_secs(0));
+    let expired = store.store_session(expired).await?.expect("cookie value");
+    let alive = store
+        .store_session(Session::new())
+        .await?
+        .expect("cookie value");
+
+    assert!(store.load_session(expired).await?.is_none());
+    assert_eq!(store.cleanup().await?, 1);
+    assert!(store.load_session(alive).await?.is_some());
+
+    Ok(())
+}
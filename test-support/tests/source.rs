@@ -0,0 +1,117 @@
+use anyhow::Result;
+use core::{services::CollectionStateService, Collector, CollectorConfig};
+use reqwest::StatusCode;
+use serde_json::json;
+use test_support::{
+    FakeServer, FakeTweet, TestApp, OTHER_TRACK_ID, OTHER_TRACK_URI, PLAYLIST_ID, TRACK_ID,
+    TRACK_URI, TWITTER_USER_ID,
+};
+
+/// いいねから集めるように切り替えると、前回読んだところまでのいいねだけを読む
+#[tokio::test]
+async fn collect_from_likes() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let client = &app.client;
+
+    let source = app.get_json("/api/source").await?;
+    assert_eq!(source["source"]["kind"], "home");
+
+    let res = client
+        .put(app.url("/api/source"))
+        .json(&json!({ "kind": "list", "id": "not-a-number" }))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let res = client
+        .put(app.url("/api/source"))
+        .json(&json!({ "kind": "likes" }))
+        .send()
+        .await?;
+    assert!(res.status().is_success(), "{}", res.status());
+    let source = app.get_json("/api/source").await?;
+    assert_eq!(source["source"]["kind"], "likes");
+
+    // いいねはツイートのIDの順に並ばない
+    let likes = format!("users/{TWITTER_USER_ID}/liked_tweets");
+    fake.push_source_tweet(&likes, FakeTweet::new("500").track(TRACK_ID));
+    let collector = Collector::new(app.state.clone(), CollectorConfig::default());
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+    let cursor = CollectionStateService::new(app.state.clone())
+        .find_cursor(TWITTER_USER_ID)
+        .await?;
+    assert_eq!(cursor.as_deref(), Some("500"));
+
+    fake.push_source_tweet(&likes, FakeTweet::new("100").track(OTHER_TRACK_ID));
+    collector.collect().await?;
+    assert_eq!(
+        fake.playlist_tracks(PLAYLIST_ID),
+        vec![TRACK_URI, OTHER_TRACK_URI]
+    );
+
+    // 切り替えると読んだところは忘れる
+    client
+        .put(app.url("/api/source"))
+        .json(&json!({ "kind": "bookmarks" }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let cursor = CollectionStateService::new(app.state.clone())
+        .find_cursor(TWITTER_USER_ID)
+        .await?;
+    assert_eq!(cursor, None);
+
+    Ok(())
+}
+
+/// `since_id` を指定できない一覧は、前回の先頭のツイートが出てくるページまで読む
+#[tokio::test]
+async fn read_likes_back_to_cursor() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    app.client
+        .put(app.url("/api/source"))
+        .json(&json!({ "kind": "likes" }))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    let likes = format!("users/{TWITTER_USER_ID}/liked_tweets");
+    fake.push_source_tweet(&likes, FakeTweet::new("50"));
+    fake.push_source_tweet(&likes, FakeTweet::new("500").track(TRACK_ID));
+    let collector = Collector::new(app.state.clone(), CollectorConfig::default());
+    collector.collect().await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    // 2ページ目の途中に前回の先頭がある
+    fake.limit_page_size(2);
+    fake.push_source_tweet(&likes, FakeTweet::new("100").track(OTHER_TRACK_ID));
+    fake.push_source_tweet(&likes, FakeTweet::new("101"));
+    fake.push_source_tweet(&likes, FakeTweet::new("102"));
+    collector.collect().await?;
+    assert_eq!(
+        fake.playlist_tracks(PLAYLIST_ID),
+        vec![TRACK_URI, OTHER_TRACK_URI]
+    );
+    let cursor = CollectionStateService::new(app.state.clone())
+        .find_cursor(TWITTER_USER_ID)
+        .await?;
+    assert_eq!(cursor.as_deref(), Some("102"));
+
+    Ok(())
+}
+
+/// Twitterを連携していなければ404を返す
+#[tokio::test]
+async fn source_without_twitter() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+
+    let res = app.client.get(app.url("/api/source")).send().await?;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
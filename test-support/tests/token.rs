@@ -0,0 +1,76 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use core::{Collector, CollectorConfig};
+use entity::{spotify_account, twitter_account};
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use test_support::{FakeServer, FakeTweet, TestApp, PLAYLIST_ID, TRACK_ID, TRACK_URI};
+
+async fn accounts(
+    connection: &DatabaseConnection,
+) -> Result<(spotify_account::Model, twitter_account::Model)> {
+    let spotify = spotify_account::Entity::find()
+        .one(connection)
+        .await?
+        .expect("spotify account is linked");
+    let twitter = twitter_account::Entity::find()
+        .one(connection)
+        .await?
+        .expect("twitter account is linked");
+    Ok((spotify, twitter))
+}
+
+/// 保存されている期限を過ぎていたら、APIを呼ぶ前にリフレッシュして保存し直す
+#[tokio::test]
+async fn refresh_expired_tokens() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let connection = &app.state.connection;
+
+    let (spotify, twitter) = accounts(connection).await?;
+    assert_eq!(spotify.access_token, "spotify-access-token");
+    assert_eq!(twitter.access_token, "twitter-access-token");
+    let expired_at = Utc::now() - Duration::minutes(5);
+    let mut spotify: spotify_account::ActiveModel = spotify.into();
+    spotify.expires_at = Set(Some(expired_at.into()));
+    spotify.update(connection).await?;
+    let mut twitter: twitter_account::ActiveModel = twitter.into();
+    twitter.expires_at = Set(Some(expired_at.into()));
+    twitter.update(connection).await?;
+
+    fake.push_tweet(FakeTweet::new("2000").track(TRACK_ID));
+    Collector::new(app.state.clone(), CollectorConfig::default())
+        .collect()
+        .await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    let (spotify, twitter) = accounts(connection).await?;
+    assert_eq!(spotify.access_token, "spotify-access-token-2");
+    assert_eq!(spotify.refresh_token, "spotify-refresh-token-2");
+    assert!(spotify.expires_at.expect("expiry is saved") > Utc::now());
+    assert_eq!(twitter.access_token, "twitter-access-token-2");
+    assert_eq!(twitter.refresh_token, "twitter-refresh-token-2");
+    assert!(twitter.expires_at.expect("expiry is saved") > Utc::now());
+    Ok(())
+}
+
+/// 期限内でも401を返されたら、リフレッシュしてもう一度だけ試す
+#[tokio::test]
+async fn refresh_on_unauthorized() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::with_linked_playlist(&fake).await?;
+    let connection = &app.state.connection;
+
+    fake.expire_access_tokens();
+    fake.push_tweet(FakeTweet::new("2000").track(TRACK_ID));
+    Collector::new(app.state.clone(), CollectorConfig::default())
+        .collect()
+        .await?;
+    assert_eq!(fake.playlist_tracks(PLAYLIST_ID), vec![TRACK_URI]);
+
+    let (spotify, twitter) = accounts(connection).await?;
+    assert_eq!(spotify.access_token, "spotify-access-token-2");
+    assert_eq!(spotify.refresh_token, "spotify-refresh-token-2");
+    assert_eq!(twitter.access_token, "twitter-access-token-2");
+    assert_eq!(twitter.refresh_token, "twitter-refresh-token-2");
+    Ok(())
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use core::services::CollectionStateService;
+use reqwest::StatusCode;
+use serde_json::json;
+use test_support::{FakeServer, TestApp, TWITTER_USER_ID};
+
+/// 他のユーザーに紐付いているTwitterのアカウントは、付け替えを指定したときだけ連携する
+#[tokio::test]
+async fn conflict_and_transfer() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let first = TestApp::start(&fake).await?;
+    first.login().await?;
+    first.link_twitter().await?;
+    first
+        .client
+        .put(first.url("/api/source"))
+        .json(&json!({ "kind": "bookmarks" }))
+        .send()
+        .await?
+        .error_for_status()?;
+    let states = CollectionStateService::new(first.state.clone());
+    states
+        .save_cursor(TWITTER_USER_ID, Some("1500".to_string()))
+        .await?;
+
+    fake.switch_spotify_user("second-spotify-user");
+    let second = first.another_browser()?;
+    second.login().await?;
+    let first_me = first.get_json("/api/me").await?;
+    let second_me = second.get_json("/api/me").await?;
+    assert_ne!(first_me["id"], second_me["id"]);
+
+    let res = second
+        .client
+        .get(second.url("/twitter/login"))
+        .send()
+        .await?;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+    let body = res.text().await?;
+    assert!(body.contains("/twitter/login?transfer=true"), "{body}");
+    // 連携しなかったトークンは失効させる
+    assert_eq!(fake.revoked_tokens(), vec!["twitter-refresh-token-2"]);
+    let first_me = first.get_json("/api/me").await?;
+    assert_eq!(first_me["twitter_account"]["user_id"], TWITTER_USER_ID);
+    let second_me = second.get_json("/api/me").await?;
+    assert!(second_me["twitter_account"].is_null());
+
+    let res = second
+        .client
+        .get(second.url("/twitter/login?transfer=true"))
+        .send()
+        .await?;
+    assert!(res.status().is_success(), "{}", res.status());
+    let first_me = first.get_json("/api/me").await?;
+    assert!(first_me["twitter_account"].is_null());
+    let second_me = second.get_json("/api/me").await?;
+    assert_eq!(second_me["twitter_account"]["user_id"], TWITTER_USER_ID);
+    // 前のユーザーの取得元や読んだところは引き継がない
+    let source = second.get_json("/api/source").await?;
+    assert_eq!(source["source"]["kind"], "home");
+    assert!(states.find(TWITTER_USER_ID).await?.is_none());
+    Ok(())
+}
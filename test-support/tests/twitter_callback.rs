@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use entity::oauth2_verifier;
+use reqwest::{header::LOCATION, StatusCode, Url};
+use sea_orm::{ActiveModelTrait, EntityTrait, Set};
+use test_support::{FakeServer, TestApp};
+
+/// `/twitter/login` から認可画面へのリダイレクトを受け取り、その `state` を返す
+async fn start_linking(app: &TestApp) -> Result<String> {
+    let res = app.get_without_redirect("/twitter/login").await?;
+    assert_eq!(res.status(), StatusCode::TEMPORARY_REDIRECT);
+    let location = res
+        .headers()
+        .get(LOCATION)
+        .context("location is none")?
+        .to_str()?;
+    let state = Url::parse(location)?
+        .query_pairs()
+        .find(|(key, _)| key == "state")
+        .map(|(_, value)| value.into_owned())
+        .context("state is none")?;
+    Ok(state)
+}
+
+/// セッションに保存した `state` と違うものでは連携しない
+#[tokio::test]
+async fn reject_state_mismatch() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+    let state = start_linking(&app).await?;
+
+    let res = app
+        .get_without_redirect(&format!(
+            "/twitter/callback?code=twitter-code&state={state}x"
+        ))
+        .await?;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    // 一度失敗したら正しい `state` でも使えない
+    let res = app
+        .get_without_redirect(&format!(
+            "/twitter/callback?code=twitter-code&state={state}"
+        ))
+        .await?;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    let me = app.get_json("/api/me").await?;
+    assert!(me["twitter_account"].is_null());
+    Ok(())
+}
+
+/// 他のユーザーが始めた認可では連携しない
+#[tokio::test]
+async fn reject_verifier_of_another_user() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+    let state = start_linking(&app).await?;
+
+    let connection = &app.state.connection;
+    let verifier = oauth2_verifier::Entity::find_by_id(state.clone())
+        .one(connection)
+        .await?
+        .expect("verifier is stored");
+    let owner = verifier.user_id.expect("verifier has the user");
+    let mut verifier: oauth2_verifier::ActiveModel = verifier.into();
+    verifier.user_id = Set(Some(owner + 1));
+    verifier.update(connection).await?;
+
+    let res = app
+        .get_without_redirect(&format!(
+            "/twitter/callback?code=twitter-code&state={state}"
+        ))
+        .await?;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let me = app.get_json("/api/me").await?;
+    assert!(me["twitter_account"].is_null());
+    // 使おうとしたverifierは消える
+    let verifier = oauth2_verifier::Entity::find_by_id(state)
+        .one(connection)
+        .await?;
+    assert!(verifier.is_none());
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use reqwest::StatusCode;
+use serde_json::json;
+use test_support::{FakeServer, TestApp, SPOTIFY_USER_ID, TWITTER_USER_ID};
+
+/// 連携を解除するとトークンが失効して、連携し直すと同じアカウントが更新される
+#[tokio::test]
+async fn unlink_and_relink_accounts() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+    app.link_twitter().await?;
+
+    let res = app.client.delete(app.url("/api/me/twitter")).send().await?;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(fake.revoked_tokens(), vec!["twitter-refresh-token"]);
+    let me = app.get_json("/api/me").await?;
+    assert!(me["twitter_account"].is_null());
+
+    let res = app.client.delete(app.url("/api/me/twitter")).send().await?;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // 2回連携しても主キーが重複せずに更新される
+    app.link_twitter().await?;
+    app.link_twitter().await?;
+    let me = app.get_json("/api/me").await?;
+    assert_eq!(me["twitter_account"]["user_id"], TWITTER_USER_ID);
+
+    let res = app.client.delete(app.url("/api/me/spotify")).send().await?;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let me = app.get_json("/api/me").await?;
+    assert!(me["spotify_account"].is_null());
+
+    // ログインしたままSpotifyを連携し直すと同じユーザーに紐付く
+    app.login().await?;
+    let relinked = app.get_json("/api/me").await?;
+    assert_eq!(relinked["id"], me["id"]);
+    assert!(!relinked["spotify_account"].is_null());
+
+    Ok(())
+}
+
+/// Spotifyの連携を解除してからログインし直すと、同じユーザーに戻って他の連携も残っている
+#[tokio::test]
+async fn relogin_after_unlinking_spotify() -> Result<()> {
+    let fake = FakeServer::start().await?;
+    fake.create_playlist("mine");
+    let app = TestApp::start(&fake).await?;
+    app.login().await?;
+    app.link_twitter().await?;
+    let res = app
+        .client
+        .put(app.url("/api/playlist"))
+        .json(&json!({ "playlist_id": "mine" }))
+        .send()
+        .await?;
+    assert!(res.status().is_success(), "{}", res.status());
+    let me = app.get_json("/api/me").await?;
+
+    let res = app.client.delete(app.url("/api/me/spotify")).send().await?;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    let res = app.client.delete(app.url("/api/me/spotify")).send().await?;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // ログアウトした状態でログインし直す
+    let other = app.another_browser()?;
+    other.login().await?;
+    let relinked = other.get_json("/api/me").await?;
+    assert_eq!(relinked["id"], me["id"]);
+    assert_eq!(relinked["spotify_account"]["user_id"], SPOTIFY_USER_ID);
+    assert_eq!(relinked["spotify_account"]["playlist_id"], "mine");
+    assert_eq!(relinked["twitter_account"]["user_id"], TWITTER_USER_ID);
+    Ok(())
+}
@@ -0,0 +1,97 @@
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use core::{OAuth2Provider, OAuth2VerifierConfig, OAuth2Verifiers};
+use entity::oauth2_verifier;
+use sea_orm::{ActiveModelTrait, DatabaseConnection, EntityTrait, Set};
+use test_support::database;
+
+fn verifiers(connection: &DatabaseConnection, provider: OAuth2Provider) -> OAuth2Verifiers {
+    OAuth2Verifiers::new(
+        connection.clone(),
+        provider,
+        &OAuth2VerifierConfig::default(),
+    )
+}
+
+/// `ttl_secs` より前に作られたことにする
+async fn expire(connection: &DatabaseConnection, state: &str) -> Result<()> {
+    let verifier = oauth2_verifier::Entity::find_by_id(state.to_string())
+        .one(connection)
+        .await?
+        .expect("verifier is stored");
+    let mut verifier: oauth2_verifier::ActiveModel = verifier.into();
+    verifier.created_at = Set((Utc::now() - Duration::minutes(11)).into());
+    verifier.update(connection).await?;
+    Ok(())
+}
+
+/// 一度取り出したら使えず、他のプロバイダーのものは取り出せない
+#[tokio::test]
+async fn remove_once() -> Result<()> {
+    let connection = database().await?;
+    let spotify = verifiers(&connection, OAuth2Provider::Spotify);
+    let twitter = verifiers(&connection, OAuth2Provider::Twitter);
+    spotify
+        .insert("state".to_string(), "verifier".to_string(), Some(1))
+        .await?;
+
+    assert!(twitter.remove("state").await.is_err());
+    let verifier = spotify.remove("state").await?;
+    assert_eq!(verifier.verifier, "verifier");
+    assert_eq!(verifier.user_id, Some(1));
+    assert!(spotify.remove("state").await.is_err());
+    Ok(())
+}
+
+/// 期限切れのものは取り出せず、そのまま消える
+#[tokio::test]
+async fn reject_expired() -> Result<()> {
+    let connection = database().await?;
+    let spotify = verifiers(&connection, OAuth2Provider::Spotify);
+    spotify
+        .insert("state".to_string(), "verifier".to_string(), None)
+        .await?;
+    expire(&connection, "state").await?;
+
+    let e = spotify
+        .remove("state")
+        .await
+        .expect_err("verifier is expired");
+    assert_eq!(e.to_string(), "Verifier is expired");
+    let stored = oauth2_verifier::Entity::find_by_id("state".to_string())
+        .one(&connection)
+        .await?;
+    assert!(stored.is_none());
+    Ok(())
+}
+
+/// 期限切れのものだけを、プロバイダーごとに消す
+#[tokio::test]
+async fn purge_expired() -> Result<()> {
+    let connection = database().await?;
+    let spotify = verifiers(&connection, OAuth2Provider::Spotify);
+    let twitter = verifiers(&connection, OAuth2Provider::Twitter);
+    for state in ["fresh", "expired"] {
+        spotify
+            .insert(state.to_string(), "verifier".to_string(), None)
+            .await?;
+    }
+    twitter
+        .insert("twitter".to_string(), "verifier".to_string(), None)
+        .await?;
+    expire(&connection, "expired").await?;
+    expire(&connection, "twitter").await?;
+
+    assert_eq!(spotify.purge().await?, 1);
+    let states = oauth2_verifier::Entity::find()
+        .all(&connection)
+        .await?
+        .into_iter()
+        .map(|verifier| verifier.state)
+        .collect::<Vec<_>>();
+    assert_eq!(states.len(), 2);
+    assert!(states.contains(&"fresh".to_string()));
+    assert!(states.contains(&"twitter".to_string()));
+    assert_eq!(twitter.purge().await?, 1);
+    Ok(())
+}